@@ -0,0 +1,164 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    ai::{AiDeathEvent, AiKind},
+    despawn_after::DespawnAfter,
+    player::Player,
+    spawning::{QueuedSpawn, SpawnDef, SpawnQueue},
+    EventTryApplyDamages, RemoveOnRespawn,
+};
+
+pub struct AffixPlugin;
+
+impl Plugin for AffixPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AffixDef>();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affix {
+    Fast,
+    Shielded,
+    Vampiric,
+    Splitting,
+    Explosive,
+}
+
+impl Affix {
+    pub const ALL: [Affix; 5] = [
+        Affix::Fast,
+        Affix::Shielded,
+        Affix::Vampiric,
+        Affix::Splitting,
+        Affix::Explosive,
+    ];
+
+    pub fn color(&self) -> Color {
+        match self {
+            Affix::Fast => Color::YELLOW * 3f32,
+            Affix::Shielded => Color::CYAN * 3f32,
+            Affix::Vampiric => Color::CRIMSON * 3f32,
+            Affix::Splitting => Color::LIME_GREEN * 3f32,
+            Affix::Explosive => Color::ORANGE_RED * 3f32,
+        }
+    }
+}
+
+#[derive(Component, Debug, Default)]
+pub struct Affixes(pub Vec<Affix>);
+
+/// Hits absorbed before the `Health` of a shielded AI goes down.
+#[derive(Component, Debug)]
+pub struct Shield {
+    pub hits: u32,
+}
+
+#[derive(Component, Debug)]
+pub struct Explosion {
+    pub radius: f32,
+}
+
+#[derive(Resource)]
+pub struct AffixDef {
+    pub base_chance: f32,
+    pub chance_per_minute: f32,
+    pub max_chance: f32,
+    /// Each extra affix is this much less likely than the previous one.
+    pub extra_affix_chance_multiplier: f32,
+    pub max_affixes: usize,
+    pub fast_speed_multiplier: f32,
+    pub shield_hits: u32,
+    pub vampiric_heal: f32,
+    pub splitting_minions: u32,
+    pub explosion_radius: f32,
+    pub explosion_damage: f32,
+}
+
+impl Default for AffixDef {
+    fn default() -> Self {
+        Self {
+            base_chance: 0.02f32,
+            chance_per_minute: 0.08f32,
+            max_chance: 0.6f32,
+            extra_affix_chance_multiplier: 0.4f32,
+            max_affixes: 3,
+            fast_speed_multiplier: 1.6f32,
+            shield_hits: 3,
+            vampiric_heal: 0.25f32,
+            splitting_minions: 3,
+            explosion_radius: 70f32,
+            explosion_damage: 0.5f32,
+        }
+    }
+}
+
+impl AffixDef {
+    pub fn roll(&self, run_seconds: f32, rng: &mut impl Rng) -> Vec<Affix> {
+        let mut chance =
+            (self.base_chance + self.chance_per_minute * run_seconds / 60f32).min(self.max_chance);
+        let mut affixes = Vec::new();
+        while affixes.len() < self.max_affixes && rng.gen_range(0f32..1f32) < chance {
+            let remaining = Affix::ALL
+                .iter()
+                .filter(|affix| !affixes.contains(*affix))
+                .copied()
+                .collect::<Vec<_>>();
+            let Some(affix) = remaining.choose(&mut *rng) else {
+                break;
+            };
+            affixes.push(*affix);
+            chance *= self.extra_affix_chance_multiplier;
+        }
+        affixes
+    }
+}
+
+pub fn affix_deaths(
+    mut commands: Commands,
+    affix_def: Res<AffixDef>,
+    spawn_def: Res<SpawnDef>,
+    mut spawn_queue: ResMut<SpawnQueue>,
+    mut ai_killed: EventReader<AiDeathEvent>,
+    mut events_try_damage: EventWriter<EventTryApplyDamages>,
+    q_players: Query<(Entity, &Transform), With<Player>>,
+) {
+    for ev in ai_killed.iter() {
+        if ev.affixes.contains(&Affix::Splitting) {
+            for i in 0..affix_def.splitting_minions {
+                let angle = std::f32::consts::TAU / affix_def.splitting_minions as f32 * i as f32;
+                spawn_queue.push(
+                    &spawn_def,
+                    QueuedSpawn {
+                        kind: AiKind::Small,
+                        position: Some(ev.origin + Vec2::from_angle(angle) * 15f32),
+                        affixes: Vec::new(),
+                        telegraph: false,
+                    },
+                );
+            }
+        }
+        if ev.affixes.contains(&Affix::Explosive) {
+            for (e, transform) in q_players.iter() {
+                if transform.translation.xy().distance(ev.origin) < affix_def.explosion_radius {
+                    events_try_damage.send(EventTryApplyDamages(
+                        e,
+                        affix_def.explosion_damage,
+                        None,
+                    ));
+                }
+            }
+            commands.spawn((
+                Transform::from_translation(ev.origin.extend(2f32)),
+                Explosion {
+                    radius: affix_def.explosion_radius,
+                },
+                DespawnAfter {
+                    timer: Timer::from_seconds(0.3_f32, TimerMode::Once),
+                },
+                RemoveOnRespawn,
+            ));
+        }
+    }
+}
@@ -3,15 +3,25 @@ use bevy::{math::Vec3Swizzles, prelude::*};
 use bevy_asset_loader::prelude::{AssetCollection, AssetCollectionApp};
 use rand::seq::SliceRandom;
 use rand::Rng;
+use serde::Deserialize;
 
+use crate::affixes::{Affix, AffixDef, Affixes, Shield};
 use crate::despawn_after::DespawnAfter;
+use crate::difficulty::DifficultyLevel;
+use crate::director::AiDirector;
 use crate::menu::GameState;
+use crate::rng::RunRng;
+use crate::run::RunScopedAppExt;
+use crate::spawning::{QueuedSpawn, SpawnDef, SpawnQueue};
+use crate::stats::{Modifier, Stat, StatSource, Stats};
+use crate::utils::{listener_position, nearest};
 
 use crate::{
-    bullets::CommandsSpawnBullet,
-    movement::{MoveSpeed, MoveTarget},
+    bullets::{BulletOwner, CommandsSpawnBullet},
+    clock::GameClock,
+    movement::{MoveDirection, MoveSpeed, MoveTarget},
     player::Player,
-    Cooldown, GameDef, Health, RemoveOnRespawn, TeamIdx, Weapon,
+    Cooldown, RemoveOnRespawn, RunTime, TeamIdx, Weapon,
 };
 
 pub struct AiPlugin;
@@ -20,6 +30,9 @@ impl Plugin for AiPlugin {
     fn build(&self, app: &mut App) {
         app.init_collection::<AiSoundAssets>();
         app.add_event::<AiDeathEvent>();
+        app.init_run_resource::<AggressionBudget>()
+            .init_run_resource::<SpawnState>()
+            .init_run_resource::<AiRetargetTimer>();
         app.add_systems(
             PostUpdate,
             handle_ai_sounds.run_if(in_state(GameState::Playing)),
@@ -36,6 +49,8 @@ pub struct AiSoundAssets {
 #[derive(Event, Debug, Default)]
 pub struct AiDeathEvent {
     pub origin: Vec2,
+    pub kind: AiKind,
+    pub affixes: Vec<Affix>,
 }
 
 #[derive(Component, Debug)]
@@ -44,192 +59,384 @@ pub struct Ai;
 #[derive(Component, Debug)]
 pub struct BigAi;
 
-pub fn spawn_ais(
-    time: Res<Time>,
-    mut commands: Commands,
-    mut timer: Local<Timer>,
-    game_settings: Res<GameDef>,
-) {
-    timer.tick(time.delta());
-    if timer.finished() {
-        timer.set_duration(bevy::utils::Duration::from_secs_f32(
-            game_settings.spawn_interval,
-        ));
-        timer.reset();
-        let mut rng = rand::thread_rng();
-        let chance = rand::thread_rng().gen_range(1..=100);
-        if chance > 30 {
-            commands.spawn((
-                Transform {
-                    translation: (Vec2::new(
-                        rng.gen_range(-460_f32..460_f32),
-                        rng.gen_range(-260_f32..260_f32),
-                    ))
-                    .extend(2f32),
-                    ..default()
-                },
-                MoveSpeed(75f32),
-                MoveTarget {
-                    target: Some(Vec2::new(200f32, 200f32)),
-                },
-                Health {
-                    current: 1f32,
-                    max: 1f32,
-                },
-                Weapon {
-                    bullets: 1u16,
-                    max: 360_u16,
-                    spread: 160_f32,
-                },
-                Cooldown {
-                    start_time: 0.0,
-                    duration: 2.0,
-                },
-                Ai,
-                TeamIdx(1),
-                RemoveOnRespawn,
-            ));
-        } else {
-            commands.spawn((
-                Transform {
-                    translation: (Vec2::new(
-                        rng.gen_range(-460_f32..460_f32),
-                        rng.gen_range(-260_f32..260_f32),
-                    ))
-                    .extend(2f32),
-                    ..default()
-                },
-                MoveSpeed(50f32),
-                MoveTarget {
-                    target: Some(Vec2::new(200f32, 200f32)),
-                },
-                Health {
-                    current: 3f32,
-                    max: 3f32,
-                },
-                Weapon {
-                    bullets: 3u16,
-                    max: 360_u16,
-                    spread: 160_f32,
-                },
-                Cooldown {
-                    start_time: 0.0,
-                    duration: 2.0,
-                },
-                Ai,
-                TeamIdx(1),
-                RemoveOnRespawn,
-                BigAi,
-            ));
+/// When an AI next wants to shoot, each AI keeps its own so volleys don't line up.
+#[derive(Component, Debug)]
+pub struct FireSchedule {
+    pub next_fire: f32,
+    pub cooldown: f32,
+    pub jitter: f32,
+}
+
+impl FireSchedule {
+    pub fn new(cooldown: f32, jitter: f32, elapsed_seconds: f32, rng: &mut impl Rng) -> Self {
+        let mut schedule = Self {
+            next_fire: 0f32,
+            cooldown,
+            jitter,
+        };
+        schedule.reschedule(elapsed_seconds, rng);
+        schedule
+    }
+
+    pub fn reschedule(&mut self, elapsed_seconds: f32, rng: &mut impl Rng) {
+        self.next_fire = elapsed_seconds + self.cooldown + rng.gen_range(0f32..=self.jitter);
+    }
+}
+
+/// Lets agile AIs sidestep player bullets that are about to hit them.
+/// `skill` is the chance to react to a threat, a failed roll still uses the cooldown.
+#[derive(Component, Debug)]
+pub struct Dodge {
+    pub skill: f32,
+    pub cooldown: f32,
+    pub last_dodge: f32,
+    pub scan_radius: f32,
+    pub distance: f32,
+}
+
+impl Dodge {
+    pub fn new(skill: f32, cooldown: f32) -> Self {
+        Self {
+            skill,
+            cooldown,
+            last_dodge: f32::MIN,
+            scan_radius: 150f32,
+            distance: 60f32,
         }
     }
+
+    fn is_ready(&self, elapsed_seconds: f32) -> bool {
+        self.last_dodge + self.cooldown < elapsed_seconds
+    }
 }
 
-pub fn update_spawn_interval(
-    time: Res<Time>,
-    mut timer: Local<Timer>,
-    mut game_settings: ResMut<GameDef>,
-) {
-    if timer.duration().as_millis() == 0 {
-        *timer = Timer::new(bevy::utils::Duration::from_secs(4), TimerMode::Repeating);
+/// Caps how many shots the whole enemy side can fire per second.
+/// The base rate comes from the difficulty curve, the director scales it.
+#[derive(Resource, Debug)]
+pub struct AggressionBudget {
+    pub multiplier: f32,
+    pub tokens: f32,
+}
+
+impl Default for AggressionBudget {
+    fn default() -> Self {
+        Self {
+            multiplier: 1f32,
+            tokens: 0f32,
+        }
     }
-    timer.tick(time.delta());
-    if timer.finished() {
-        game_settings.spawn_interval *= game_settings.spawn_interval_multiplier_per_second;
-        println!("spawn_interval {}", game_settings.spawn_interval);
+}
+
+impl AggressionBudget {
+    pub fn tick(&mut self, delta_seconds: f32, base_shots_per_second: f32) {
+        let shots_per_second = base_shots_per_second * self.multiplier;
+        // Unused shots only pile up to one second worth, to avoid huge bursts.
+        self.tokens =
+            (self.tokens + shots_per_second * delta_seconds).min(shots_per_second.max(1f32));
     }
 }
 
-pub fn ai_move(
-    time: Res<Time>,
-    mut q_moves: Query<&mut MoveTarget, With<Ai>>,
-    mut q_player: Query<&Transform, With<Player>>,
-    mut timer: Local<Timer>,
-) {
-    if timer.duration().as_millis() == 0 {
-        *timer = Timer::new(
+/// Timer driven spawning of the endless modes.
+#[derive(Resource, Default)]
+pub struct SpawnState {
+    /// Starts finished so the first AI shows up right away
+    pub spawn_timer: Timer,
+}
+
+#[derive(Resource)]
+pub struct AiRetargetTimer(pub Timer);
+
+impl Default for AiRetargetTimer {
+    fn default() -> Self {
+        Self(Timer::new(
             bevy::utils::Duration::from_millis(2360),
             TimerMode::Repeating,
+        ))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum AiKind {
+    #[default]
+    Small,
+    Big,
+}
+
+pub fn spawn_ai(
+    commands: &mut Commands,
+    kind: AiKind,
+    position: Vec2,
+    affixes: Vec<Affix>,
+    affix_def: &AffixDef,
+    health_multiplier: f32,
+    elapsed_seconds: f32,
+    rng: &mut impl Rng,
+) -> Entity {
+    let (base_health, base_speed, base_bullets) = match kind {
+        AiKind::Small => (1f32, 75f32, 1f32),
+        AiKind::Big => (3f32, 50f32, 3f32),
+    };
+    let mut stats = Stats::default()
+        .with_base(Stat::MaxHealth, base_health)
+        .with_base(Stat::MoveSpeed, base_speed)
+        .with_base(Stat::Bullets, base_bullets)
+        .with_base(Stat::FireCooldown, 2f32)
+        .with_modifier(
+            StatSource::Difficulty,
+            Stat::MaxHealth,
+            Modifier::Multiply(health_multiplier),
+        );
+    if affixes.contains(&Affix::Fast) {
+        stats.add_modifier(
+            StatSource::Affix(Affix::Fast),
+            Stat::MoveSpeed,
+            Modifier::Multiply(affix_def.fast_speed_multiplier),
         );
     }
-    timer.tick(time.delta());
-    if !timer.just_finished() {
-        return;
+    let mut entity = match kind {
+        AiKind::Small => commands.spawn((
+            Transform {
+                translation: position.extend(2f32),
+                ..default()
+            },
+            stats.move_speed(),
+            MoveTarget {
+                target: Some(Vec2::new(200f32, 200f32)),
+            },
+            stats.health(),
+            stats.weapon(360_u16, 160_f32),
+            stats.cooldown(),
+            FireSchedule::new(2.5f32, 2f32, elapsed_seconds, rng),
+            Dodge::new(0.35f32, 2.5f32),
+            Ai,
+            TeamIdx(1),
+            RemoveOnRespawn,
+        )),
+        AiKind::Big => commands.spawn((
+            Transform {
+                translation: position.extend(2f32),
+                ..default()
+            },
+            stats.move_speed(),
+            MoveTarget {
+                target: Some(Vec2::new(200f32, 200f32)),
+            },
+            stats.health(),
+            stats.weapon(360_u16, 160_f32),
+            stats.cooldown(),
+            FireSchedule::new(3.5f32, 2f32, elapsed_seconds, rng),
+            Ai,
+            TeamIdx(1),
+            RemoveOnRespawn,
+            BigAi,
+        )),
+    };
+    if affixes.contains(&Affix::Shielded) {
+        entity.insert(Shield {
+            hits: affix_def.shield_hits,
+        });
     }
-    let Some(player_position) = q_player.iter().next() else {
+    if !affixes.is_empty() {
+        entity.insert(Affixes(affixes));
+    }
+    entity.insert(stats);
+    entity.id()
+}
+
+pub fn spawn_ais(
+    clock: Res<GameClock>,
+    mut spawn_state: ResMut<SpawnState>,
+    director: Res<AiDirector>,
+    level: Res<DifficultyLevel>,
+    affix_def: Res<AffixDef>,
+    spawn_def: Res<SpawnDef>,
+    run_time: Res<RunTime>,
+    mut spawn_queue: ResMut<SpawnQueue>,
+    mut run_rng: ResMut<RunRng>,
+) {
+    spawn_state.spawn_timer.tick(clock.delta());
+    if spawn_state.spawn_timer.finished() {
+        spawn_state
+            .spawn_timer
+            .set_duration(bevy::utils::Duration::from_secs_f32(
+                level.spawn_interval / director.spawn_rate_multiplier,
+            ));
+        spawn_state.spawn_timer.reset();
+        let rng = &mut run_rng.0;
+        let big_ai_chance = level.big_ai_chance * director.big_ai_chance_multiplier;
+        let kind = if rng.gen_range(0f32..1f32) >= big_ai_chance {
+            AiKind::Small
+        } else {
+            AiKind::Big
+        };
+        spawn_queue.push(
+            &spawn_def,
+            QueuedSpawn {
+                kind,
+                position: None,
+                affixes: affix_def.roll(run_time.0.elapsed_secs(), rng),
+                telegraph: true,
+            },
+        );
+    }
+}
+
+pub fn ai_move(
+    clock: Res<GameClock>,
+    mut q_moves: Query<(&Transform, &mut MoveTarget), With<Ai>>,
+    q_player: Query<&Transform, With<Player>>,
+    mut timer: ResMut<AiRetargetTimer>,
+    mut run_rng: ResMut<RunRng>,
+) {
+    timer.0.tick(clock.delta());
+    if !timer.0.just_finished() {
         return;
-    };
-    let mut rng = rand::thread_rng();
-    for mut m in q_moves.iter_mut() {
+    }
+    let players = q_player
+        .iter()
+        .map(|transform| transform.translation.xy())
+        .collect::<Vec<_>>();
+    let rng = &mut run_rng.0;
+    for (transform, mut m) in q_moves.iter_mut() {
+        let Some(player_position) = nearest(&players, transform.translation.xy()) else {
+            return;
+        };
         let t = rng.gen_range(0f32..1f32) * std::f32::consts::TAU;
         let offset = Vec2::new(t.cos(), t.sin()) * 200f32;
-        m.target = Some(player_position.translation.xy() + offset);
+        m.target = Some(player_position + offset);
     }
 }
 
 pub fn ai_fire(
     mut commands: Commands,
-    time: Res<Time>,
+    clock: Res<GameClock>,
+    mut budget: ResMut<AggressionBudget>,
+    level: Res<DifficultyLevel>,
+    mut run_rng: ResMut<RunRng>,
     mut q_attackers: Query<
         (
             Entity,
             &Transform,
-            &MoveTarget,
             &TeamIdx,
             &Cooldown,
             &Weapon,
+            &mut FireSchedule,
         ),
         With<Ai>,
     >,
-    mut q_player: Query<&Transform, With<Player>>,
-    mut timer: Local<Timer>,
+    q_player: Query<&Transform, With<Player>>,
 ) {
-    if timer.duration().as_millis() == 0 {
-        *timer = Timer::new(bevy::utils::Duration::from_secs(1), TimerMode::Repeating);
-    }
-    timer.tick(time.delta());
-    if !timer.just_finished() {
+    budget.tick(clock.delta_seconds(), level.shots_per_second);
+    let players = q_player
+        .iter()
+        .map(|transform| transform.translation.xy())
+        .collect::<Vec<_>>();
+    if players.is_empty() {
         return;
     }
-    let Some(player_position) = q_player.iter().next() else {
-        return;
-    };
-    let elapsed_seconds = time.elapsed_seconds();
-    let mut rng: rand::rngs::ThreadRng = rand::thread_rng();
+    let elapsed_seconds = clock.elapsed_seconds();
+    let rng = &mut run_rng.0;
     let mut ais = q_attackers
         .iter_mut()
-        .filter(|ai| ai.4.is_ready(elapsed_seconds))
+        .filter(|ai| ai.5.next_fire <= elapsed_seconds && ai.3.is_ready(elapsed_seconds))
         .collect::<Vec<_>>();
-    ais.shuffle(&mut rng);
+    // Ready AIs compete for the budget, shuffle so the same ones don't always win.
+    ais.shuffle(rng);
 
-    let f32_number = elapsed_seconds / 35.0; // One extra one will shoot per every 35 seconds
-
-    let amount_to_shoot: f32 = f32_number.clamp(1.0, 25.0);
-
-    for (entity, transform, _, team, cooldown, weapon) in ais.iter().take(amount_to_shoot as usize)
-    {
+    for (entity, transform, team, cooldown, weapon, mut schedule) in ais {
+        if budget.tokens < 1f32 {
+            break;
+        }
         let dot = rng.gen_range(0f32..1f32) * std::f32::consts::TAU;
         let offset = Vec2::new(dot.cos(), dot.sin()) * 50f32;
 
         let t_position = transform.translation.xy();
+        let Some(player_position) = nearest(&players, t_position) else {
+            break;
+        };
         if commands
             .spawn_bullet(
-                *entity,
+                entity,
                 t_position,
-                ((player_position.translation.xy() + offset) - t_position).normalize_or_zero(),
-                (*team).clone(),
+                ((player_position + offset) - t_position).normalize_or_zero(),
+                *team,
                 cooldown,
-                &time,
+                &clock,
                 weapon.bullets,
                 weapon.spread,
             )
             .is_ok()
         {
-            commands.entity(*entity).insert(Cooldown {
-                start_time: time.elapsed_seconds(),
+            budget.tokens -= 1f32;
+            commands.entity(entity).insert(Cooldown {
+                start_time: clock.elapsed_seconds(),
                 duration: cooldown.duration,
             });
         }
+        schedule.reschedule(elapsed_seconds, rng);
+    }
+}
+
+pub fn ai_dodge(
+    clock: Res<GameClock>,
+    mut run_rng: ResMut<RunRng>,
+    mut q_dodgers: Query<(&Transform, &TeamIdx, &mut MoveTarget, &mut Dodge), With<Ai>>,
+    q_bullets: Query<(&Transform, &TeamIdx, &MoveDirection, &MoveSpeed), With<BulletOwner>>,
+) {
+    // Same radius as `collisions_bullet_health`, plus a bit of margin.
+    const HIT_RADIUS: f32 = 24f32;
+    // Bullets further than this in time are ignored, it leaves room to get away.
+    const HORIZON_SECONDS: f32 = 0.6f32;
+
+    let elapsed_seconds = clock.elapsed_seconds();
+    let rng = &mut run_rng.0;
+    for (transform, team, mut move_target, mut dodge) in q_dodgers.iter_mut() {
+        if !dodge.is_ready(elapsed_seconds) {
+            continue;
+        }
+        let position = transform.translation.xy();
+        let mut closest_threat: Option<(f32, Vec2, Vec2)> = None;
+        for (bullet_transform, bullet_team, direction, speed) in q_bullets.iter() {
+            if bullet_team.0 == team.0 {
+                continue;
+            }
+            let to_ai = position - bullet_transform.translation.xy();
+            if to_ai.length_squared() > dodge.scan_radius * dodge.scan_radius {
+                continue;
+            }
+            let velocity = direction.0 * speed.0;
+            let speed_squared = velocity.length_squared();
+            if speed_squared == 0f32 {
+                continue;
+            }
+            let time_to_closest = to_ai.dot(velocity) / speed_squared;
+            if !(0f32..HORIZON_SECONDS).contains(&time_to_closest) {
+                continue;
+            }
+            let miss = to_ai - velocity * time_to_closest;
+            if miss.length() > HIT_RADIUS {
+                continue;
+            }
+            if closest_threat.map_or(true, |(t, _, _)| time_to_closest < t) {
+                closest_threat = Some((time_to_closest, direction.0, miss));
+            }
+        }
+        let Some((_, bullet_direction, miss)) = closest_threat else {
+            continue;
+        };
+        dodge.last_dodge = elapsed_seconds;
+        if rng.gen_range(0f32..1f32) >= dodge.skill {
+            continue;
+        }
+        // Step out on the side the bullet would already miss on, head-on shots pick a side.
+        let side = if miss.length_squared() > 0.01f32 {
+            miss.normalize()
+        } else if rng.gen_bool(0.5) {
+            bullet_direction.perp()
+        } else {
+            -bullet_direction.perp()
+        };
+        move_target.target = Some(position + side * dodge.distance);
     }
 }
 
@@ -240,7 +447,7 @@ pub fn handle_ai_sounds(
     listener: Query<&Transform, With<Player>>,
 ) {
     for e in damaged_events.iter() {
-        let Ok(listener) = listener.get_single() else {
+        let Some(listener) = listener_position(listener.iter()) else {
             return;
         };
         commands.spawn((
@@ -250,8 +457,7 @@ pub fn handle_ai_sounds(
                 spatial: SpatialSettings::new(
                     Transform::IDENTITY,
                     5f32,
-                    ((e.origin - listener.translation.xy()).normalize_or_zero() * (5f32 / 2f32))
-                        .extend(0f32),
+                    ((e.origin - listener).normalize_or_zero() * (5f32 / 2f32)).extend(0f32),
                 ),
             },
             DespawnAfter {
@@ -0,0 +1,134 @@
+use bevy::audio::Volume;
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_asset_loader::prelude::{AssetCollection, AssetCollectionApp};
+
+use crate::{
+    affixes::Explosion, ai::Ai, bullets::BulletOwner, controls::PlayerInput,
+    despawn_after::DespawnAfter, menu::GameState, player::Player, EventTryApplyDamages,
+    RemoveOnRespawn, TeamIdx,
+};
+
+pub struct BombPlugin;
+
+impl Plugin for BombPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_collection::<BombAssets>()
+            .init_resource::<BombDef>()
+            .add_event::<BombDetonatedEvent>()
+            .add_event::<BombPickupEvent>()
+            .add_systems(
+                PostUpdate,
+                handle_bomb_sounds.run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct BombAssets {
+    #[asset(path = "sounds/explosion-3.ogg")]
+    detonation: Handle<AudioSource>,
+    #[asset(path = "sounds/hit-close-1.ogg")]
+    pickup: Handle<AudioSource>,
+}
+
+#[derive(Resource)]
+pub struct BombDef {
+    pub key: KeyCode,
+    pub radius: f32,
+    pub damage: f32,
+}
+
+impl Default for BombDef {
+    fn default() -> Self {
+        Self {
+            key: KeyCode::Q,
+            radius: 350f32,
+            damage: 3f32,
+        }
+    }
+}
+
+/// Bombs carried by a player, picked up from `PickupKind::Bomb`.
+#[derive(Component, Debug)]
+pub struct Bombs {
+    pub count: u32,
+    pub max: u32,
+}
+
+#[derive(Event, Debug)]
+pub struct BombDetonatedEvent {
+    pub origin: Vec2,
+}
+
+#[derive(Event, Debug, Default)]
+pub struct BombPickupEvent;
+
+/// Clears every enemy bullet and hurts the enemies around the player.
+pub fn trigger_bomb(
+    mut commands: Commands,
+    bomb_def: Res<BombDef>,
+    mut detonated_events: EventWriter<BombDetonatedEvent>,
+    mut events_try_damage: EventWriter<EventTryApplyDamages>,
+    mut q_bombers: Query<(Entity, &Transform, &PlayerInput, &TeamIdx, &mut Bombs), With<Player>>,
+    q_bullets: Query<(Entity, &TeamIdx), With<BulletOwner>>,
+    q_ais: Query<(Entity, &Transform), With<Ai>>,
+) {
+    for (e, transform, input, team, mut bombs) in q_bombers.iter_mut() {
+        if !input.bomb || bombs.count == 0 {
+            continue;
+        }
+        bombs.count -= 1;
+        let origin = transform.translation.xy();
+        for (bullet, bullet_team) in q_bullets.iter() {
+            if bullet_team.0 != team.0 {
+                commands.entity(bullet).despawn();
+            }
+        }
+        for (ai, ai_transform) in q_ais.iter() {
+            if ai_transform.translation.xy().distance(origin) < bomb_def.radius {
+                events_try_damage.send(EventTryApplyDamages(ai, bomb_def.damage, Some(e)));
+            }
+        }
+        commands.spawn((
+            Transform::from_translation(origin.extend(3f32)),
+            Explosion {
+                radius: bomb_def.radius,
+            },
+            DespawnAfter {
+                timer: Timer::from_seconds(0.6f32, TimerMode::Once),
+            },
+            RemoveOnRespawn,
+        ));
+        detonated_events.send(BombDetonatedEvent { origin });
+    }
+}
+
+pub fn handle_bomb_sounds(
+    bomb_assets: Res<BombAssets>,
+    mut commands: Commands,
+    mut detonated_events: EventReader<BombDetonatedEvent>,
+    mut pickup_events: EventReader<BombPickupEvent>,
+) {
+    for _ in pickup_events.iter() {
+        commands.spawn((
+            AudioBundle {
+                source: bomb_assets.pickup.clone(),
+                settings: PlaybackSettings::ONCE.with_volume(Volume::new_relative(5.0)),
+            },
+            DespawnAfter {
+                timer: Timer::from_seconds(2_f32, TimerMode::Once),
+            },
+        ));
+    }
+    for _ in detonated_events.iter() {
+        commands.spawn((
+            AudioBundle {
+                source: bomb_assets.detonation.clone(),
+                settings: PlaybackSettings::ONCE.with_volume(Volume::new_relative(8.0)),
+            },
+            DespawnAfter {
+                timer: Timer::from_seconds(2_f32, TimerMode::Once),
+            },
+        ));
+    }
+}
@@ -2,10 +2,13 @@ use bevy::{ecs::system::Command, math::Vec3Swizzles, prelude::*};
 use bevy_asset_loader::prelude::*;
 
 use crate::{
+    clock::GameClock,
     despawn_after::DespawnAfter,
     menu::GameState,
     movement::{MoveDirection, MoveSpeed},
     player::Player,
+    upgrades::Pierce,
+    utils::listener_position,
     Cooldown, RemoveOnRespawn, TeamIdx,
 };
 
@@ -46,12 +49,22 @@ pub struct BulletOwner {
     pub entity: Entity,
 }
 
+/// Enemies a bullet can still go through, and the ones it already hit.
+#[derive(Component, Debug)]
+pub struct Piercing {
+    pub remaining: u32,
+    pub hit: Vec<Entity>,
+}
+
 impl Command for SpawnBulletCommand {
     fn apply(self, world: &mut World) {
+        let pierce = world
+            .get::<Pierce>(self.from_entity)
+            .map_or(0, |pierce| pierce.0);
         for n in 0..self.number_of_bullets as i16 {
             let rotation =
                 Vec2::from_angle(std::f32::consts::TAU / self.number_of_bullets as f32 * n as f32);
-            world.spawn((
+            let mut bullet = world.spawn((
                 Transform {
                     translation: self.from_position.extend(2f32),
                     ..default()
@@ -67,6 +80,12 @@ impl Command for SpawnBulletCommand {
                 self.team,
                 RemoveOnRespawn,
             ));
+            if pierce > 0 {
+                bullet.insert(Piercing {
+                    remaining: pierce,
+                    hit: Vec::new(),
+                });
+            }
         }
         world.send_event(EventBulletSpawn {
             origin: self.from_position,
@@ -82,7 +101,7 @@ pub trait CommandsSpawnBullet {
         direction: Vec2,
         team: TeamIdx,
         cooldown: &Cooldown,
-        time: &Res<Time>,
+        clock: &GameClock,
         number_of_bullets: u16,
         bullet_spread: f32,
     ) -> Result<&mut Self, ()>;
@@ -96,14 +115,14 @@ impl CommandsSpawnBullet for Commands<'_, '_> {
         direction: Vec2,
         team: TeamIdx,
         cooldown: &Cooldown,
-        time: &Res<Time>,
+        clock: &GameClock,
         number_of_bullets: u16,
         bullet_spread: f32,
     ) -> Result<&mut Self, ()> {
         if direction == Vec2::ZERO {
             return Err(());
         }
-        if time.elapsed_seconds() < cooldown.start_time + cooldown.duration {
+        if clock.elapsed_seconds() < cooldown.start_time + cooldown.duration {
             return Err(());
         }
 
@@ -126,7 +145,10 @@ fn bullet_sounds(
     listener: Query<&Transform, With<Player>>,
 ) {
     for e in ev_bullets.iter() {
-        let listener = listener.single();
+        // No listener while the players wait to respawn
+        let Some(listener) = listener_position(listener.iter()) else {
+            return;
+        };
         commands.spawn((
             SpatialAudioBundle {
                 source: bullet_assets.pew1.clone(),
@@ -134,8 +156,7 @@ fn bullet_sounds(
                 spatial: SpatialSettings::new(
                     Transform::IDENTITY,
                     5f32,
-                    ((e.origin - listener.translation.xy()).normalize_or_zero() * (5f32 / 2f32))
-                        .extend(0f32),
+                    ((e.origin - listener).normalize_or_zero() * (5f32 / 2f32)).extend(0f32),
                 ),
             },
             DespawnAfter {
@@ -0,0 +1,58 @@
+use bevy::{prelude::*, utils::Duration};
+
+use crate::menu::GameState;
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameClock>()
+            .add_systems(PreUpdate, advance_game_clock);
+    }
+}
+
+/// Gameplay time, it only moves forward while playing and can be slowed down.
+/// Use it instead of `Time` for anything that should stop when the game is paused.
+#[derive(Resource, Debug)]
+pub struct GameClock {
+    elapsed: Duration,
+    delta: Duration,
+    pub time_scale: f32,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            delta: Duration::ZERO,
+            time_scale: 1f32,
+        }
+    }
+}
+
+impl GameClock {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+}
+
+fn advance_game_clock(
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
+    mut clock: ResMut<GameClock>,
+) {
+    if game_state.get() != &GameState::Playing {
+        clock.delta = Duration::ZERO;
+        return;
+    }
+    clock.delta = time.delta().mul_f32(clock.time_scale.max(0f32));
+    clock.elapsed += clock.delta;
+}
@@ -0,0 +1,268 @@
+use bevy::{ecs::system::SystemParam, math::Vec3Swizzles, prelude::*, window::PrimaryWindow};
+
+use crate::{
+    bombs::BombDef,
+    dash::DashDef,
+    player::{Coop, PlayerSlot},
+};
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ControlsDef>();
+    }
+}
+
+/// Keys of the second player, and the gamepad bindings of everyone.
+#[derive(Resource)]
+pub struct ControlsDef {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub fire: KeyCode,
+    pub dash: KeyCode,
+    pub bomb: KeyCode,
+    pub dash_button: GamepadButtonType,
+    pub bomb_button: GamepadButtonType,
+    /// The left stick is ignored below that, so a worn stick doesn't drift.
+    pub move_deadzone: f32,
+    /// How far the right stick has to be pushed to shoot.
+    pub fire_deadzone: f32,
+}
+
+impl Default for ControlsDef {
+    fn default() -> Self {
+        Self {
+            up: KeyCode::I,
+            down: KeyCode::K,
+            left: KeyCode::J,
+            right: KeyCode::L,
+            fire: KeyCode::U,
+            dash: KeyCode::O,
+            bomb: KeyCode::P,
+            dash_button: GamepadButtonType::RightTrigger,
+            bomb_button: GamepadButtonType::LeftTrigger,
+            move_deadzone: 0.15f32,
+            fire_deadzone: 0.5f32,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controls {
+    /// WASD/arrows to move, the mouse to aim and shoot.
+    KeyboardMouse,
+    /// `ControlsDef` keys, shots go where the player last moved.
+    Alternate,
+}
+
+/// What a player wants to do this frame, read from its `Controls`.
+#[derive(Component, Debug)]
+pub struct PlayerInput {
+    pub movement: Vec2,
+    pub aim: Vec2,
+    pub fire: bool,
+    pub dash: bool,
+    pub bomb: bool,
+}
+
+impl Default for PlayerInput {
+    fn default() -> Self {
+        Self {
+            movement: Vec2::ZERO,
+            aim: Vec2::Y,
+            fire: false,
+            dash: false,
+            bomb: false,
+        }
+    }
+}
+
+fn axis(keyboard_input: &Input<KeyCode>, negative: &[KeyCode], positive: &[KeyCode]) -> f32 {
+    let mut value = 0f32;
+    if keyboard_input.any_pressed(negative.iter().copied()) {
+        value -= 1f32;
+    }
+    if keyboard_input.any_pressed(positive.iter().copied()) {
+        value += 1f32;
+    }
+    value
+}
+
+/// Gamepad used by a slot, the players without a mouse get theirs first.
+/// It only depends on the slot, so a gamepad stays with its player while
+/// the other one waits to respawn.
+fn gamepad_index(slot: PlayerSlot, coop: &Coop) -> usize {
+    let players = coop.players();
+    (slot.0 + players - 1) % players
+}
+
+/// A player with a gamepad can use it on top of their keys.
+pub fn read_player_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    controls_def: Res<ControlsDef>,
+    coop: Res<Coop>,
+    dash_def: Res<DashDef>,
+    bomb_def: Res<BombDef>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&GlobalTransform, &Camera)>,
+    mut q_players: Query<(&Transform, &Controls, &PlayerSlot, &mut PlayerInput)>,
+) {
+    let cursor = q_windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+        .zip(camera.iter().next())
+        .and_then(|(position, (camera_transform, camera))| {
+            camera.viewport_to_world_2d(camera_transform, position)
+        });
+    let mut gamepads = gamepads.iter().collect::<Vec<_>>();
+    gamepads.sort_by_key(|gamepad| gamepad.id);
+
+    for (transform, controls, slot, mut input) in q_players.iter_mut() {
+        let gamepad = gamepads.get(gamepad_index(*slot, &coop)).copied();
+        let stick = |x, y| {
+            gamepad.map_or(Vec2::ZERO, |gamepad| {
+                Vec2::new(
+                    gamepad_axes
+                        .get(GamepadAxis::new(gamepad, x))
+                        .unwrap_or(0f32),
+                    gamepad_axes
+                        .get(GamepadAxis::new(gamepad, y))
+                        .unwrap_or(0f32),
+                )
+            })
+        };
+        let gamepad_just_pressed = |button_type| {
+            gamepad.is_some_and(|gamepad| {
+                gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type))
+            })
+        };
+
+        let (keys, fire, dash, bomb) = match controls {
+            Controls::KeyboardMouse => {
+                if let Some(cursor) = cursor {
+                    input.aim = (cursor - transform.translation.xy()).normalize_or_zero();
+                }
+                (
+                    Vec2::new(
+                        axis(
+                            &keyboard_input,
+                            &[KeyCode::A, KeyCode::Left],
+                            &[KeyCode::D, KeyCode::Right],
+                        ),
+                        axis(
+                            &keyboard_input,
+                            &[KeyCode::S, KeyCode::Down],
+                            &[KeyCode::W, KeyCode::Up],
+                        ),
+                    ),
+                    buttons.pressed(MouseButton::Left) && cursor.is_some(),
+                    keyboard_input.just_pressed(dash_def.key)
+                        || buttons.just_pressed(dash_def.button),
+                    keyboard_input.just_pressed(bomb_def.key),
+                )
+            }
+            Controls::Alternate => (
+                Vec2::new(
+                    axis(&keyboard_input, &[controls_def.left], &[controls_def.right]),
+                    axis(&keyboard_input, &[controls_def.down], &[controls_def.up]),
+                ),
+                keyboard_input.pressed(controls_def.fire),
+                keyboard_input.just_pressed(controls_def.dash),
+                keyboard_input.just_pressed(controls_def.bomb),
+            ),
+        };
+
+        let left_stick = stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+        input.movement = if keys != Vec2::ZERO {
+            keys.normalize()
+        } else if left_stick.length() > controls_def.move_deadzone {
+            left_stick.clamp_length_max(1f32)
+        } else {
+            Vec2::ZERO
+        };
+        if *controls == Controls::Alternate && input.movement != Vec2::ZERO {
+            input.aim = input.movement.normalize();
+        }
+        // Twin-stick, pushing the right stick past the deadzone aims and shoots
+        let right_stick = stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+        let stick_fire = right_stick.length() > controls_def.fire_deadzone;
+        if stick_fire {
+            input.aim = right_stick.normalize();
+        }
+        input.fire = fire || stick_fire;
+        input.dash = dash || gamepad_just_pressed(controls_def.dash_button);
+        input.bomb = bomb || gamepad_just_pressed(controls_def.bomb_button);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    /// Pauses, resumes and leaves the game over screen.
+    Confirm,
+    /// Picks the highlighted upgrade.
+    Select,
+    Left,
+    Right,
+    Up,
+    Down,
+    Difficulty,
+    Coop,
+    ShopNext,
+    Buy,
+}
+
+impl MenuAction {
+    pub fn key(&self) -> KeyCode {
+        match self {
+            MenuAction::Confirm | MenuAction::Select => KeyCode::Return,
+            MenuAction::Left => KeyCode::Left,
+            MenuAction::Right => KeyCode::Right,
+            MenuAction::Up => KeyCode::Up,
+            MenuAction::Down => KeyCode::Down,
+            MenuAction::Difficulty => KeyCode::D,
+            MenuAction::Coop => KeyCode::C,
+            MenuAction::ShopNext => KeyCode::Tab,
+            MenuAction::Buy => KeyCode::B,
+        }
+    }
+
+    pub fn button(&self) -> GamepadButtonType {
+        match self {
+            MenuAction::Confirm => GamepadButtonType::Start,
+            MenuAction::Select | MenuAction::Buy => GamepadButtonType::South,
+            MenuAction::Left => GamepadButtonType::DPadLeft,
+            MenuAction::Right => GamepadButtonType::DPadRight,
+            MenuAction::Up => GamepadButtonType::DPadUp,
+            MenuAction::Down => GamepadButtonType::DPadDown,
+            MenuAction::Difficulty => GamepadButtonType::West,
+            MenuAction::Coop => GamepadButtonType::North,
+            MenuAction::ShopNext => GamepadButtonType::RightTrigger,
+        }
+    }
+}
+
+/// Menus listen to the keyboard and to every gamepad at once.
+#[derive(SystemParam)]
+pub struct MenuInput<'w> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+}
+
+impl MenuInput<'_> {
+    pub fn just_pressed(&self, action: MenuAction) -> bool {
+        self.keyboard_input.just_pressed(action.key())
+            || self.gamepads.iter().any(|gamepad| {
+                self.gamepad_buttons
+                    .just_pressed(GamepadButton::new(gamepad, action.button()))
+            })
+    }
+}
@@ -0,0 +1,156 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    clock::GameClock,
+    controls::PlayerInput,
+    despawn_after::DespawnAfter,
+    menu::LastActivity,
+    movement::MoveDirection,
+    player::{Player, PlayerSlot},
+    RemoveOnRespawn,
+};
+
+pub struct DashPlugin;
+
+impl Plugin for DashPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DashDef>();
+    }
+}
+
+#[derive(Resource)]
+pub struct DashDef {
+    pub key: KeyCode,
+    pub button: MouseButton,
+    pub seconds: f32,
+    /// Damage is ignored for that long after a dash starts.
+    pub invulnerable_seconds: f32,
+    pub afterimage_interval: f32,
+    pub afterimage_seconds: f32,
+}
+
+impl Default for DashDef {
+    fn default() -> Self {
+        Self {
+            key: KeyCode::Space,
+            button: MouseButton::Right,
+            seconds: 0.15f32,
+            invulnerable_seconds: 0.3f32,
+            afterimage_interval: 0.03f32,
+            afterimage_seconds: 0.25f32,
+        }
+    }
+}
+
+/// Dash stats of a player, upgrades change `distance` and `cooldown`.
+#[derive(Component, Debug)]
+pub struct Dash {
+    pub distance: f32,
+    pub cooldown: f32,
+    pub start_time: f32,
+}
+
+impl Default for Dash {
+    fn default() -> Self {
+        Self {
+            distance: 90f32,
+            cooldown: 1.2f32,
+            start_time: f32::NEG_INFINITY,
+        }
+    }
+}
+
+/// A dash in progress.
+#[derive(Component, Debug)]
+pub struct Dashing {
+    pub velocity: Vec2,
+    pub timer: Timer,
+    pub afterimage_timer: Timer,
+}
+
+#[derive(Component, Debug)]
+pub struct Invulnerable(pub Timer);
+
+#[derive(Component, Debug)]
+pub struct Afterimage(pub PlayerSlot);
+
+pub fn trigger_dash(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    dash_def: Res<DashDef>,
+    mut last_activity: ResMut<LastActivity>,
+    mut q_dashers: Query<
+        (
+            Entity,
+            &MoveDirection,
+            &PlayerInput,
+            &mut Dash,
+            Option<&Invulnerable>,
+        ),
+        (With<Player>, Without<Dashing>),
+    >,
+) {
+    for (e, direction, input, mut dash, invulnerable) in q_dashers.iter_mut() {
+        // Standing still has no direction to dash along
+        if !input.dash
+            || direction.0 == Vec2::ZERO
+            || clock.elapsed_seconds() < dash.start_time + dash.cooldown
+        {
+            continue;
+        }
+        dash.start_time = clock.elapsed_seconds();
+        last_activity.0.reset();
+        // Dashing right after a respawn keeps the longer respawn i-frames
+        let invulnerable_seconds = invulnerable.map_or(dash_def.invulnerable_seconds, |i| {
+            i.0.remaining_secs().max(dash_def.invulnerable_seconds)
+        });
+        commands.entity(e).insert((
+            Dashing {
+                // A half pushed stick still dashes the full distance
+                velocity: direction.0.normalize() * dash.distance / dash_def.seconds,
+                timer: Timer::from_seconds(dash_def.seconds, TimerMode::Once),
+                afterimage_timer: Timer::from_seconds(
+                    dash_def.afterimage_interval,
+                    TimerMode::Repeating,
+                ),
+            },
+            Invulnerable(Timer::from_seconds(invulnerable_seconds, TimerMode::Once)),
+        ));
+    }
+}
+
+pub fn dash_movement(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    dash_def: Res<DashDef>,
+    mut q_dashing: Query<(Entity, &mut Transform, &mut Dashing, &PlayerSlot)>,
+) {
+    for (e, mut transform, mut dashing, slot) in q_dashing.iter_mut() {
+        transform.translation += (dashing.velocity * clock.delta_seconds()).extend(0f32);
+        if dashing.afterimage_timer.tick(clock.delta()).just_finished() {
+            commands.spawn((
+                Transform::from_translation(transform.translation.xy().extend(1f32)),
+                Afterimage(*slot),
+                DespawnAfter {
+                    timer: Timer::from_seconds(dash_def.afterimage_seconds, TimerMode::Once),
+                },
+                RemoveOnRespawn,
+            ));
+        }
+        if dashing.timer.tick(clock.delta()).finished() {
+            commands.entity(e).remove::<Dashing>();
+        }
+    }
+}
+
+pub fn expire_invulnerability(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut q_invulnerable: Query<(Entity, &mut Invulnerable)>,
+) {
+    for (e, mut invulnerable) in q_invulnerable.iter_mut() {
+        if invulnerable.0.tick(clock.delta()).finished() {
+            commands.entity(e).remove::<Invulnerable>();
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::clock::GameClock;
 use crate::menu::GameState;
 
 pub struct DespawnAfterPlugin;
@@ -20,11 +21,11 @@ pub struct DespawnAfter {
 
 fn despawn_after(
     mut commands: Commands,
-    time: Res<Time>,
+    clock: Res<GameClock>,
     mut q_des: Query<(Entity, &mut DespawnAfter)>,
 ) {
     for (e, mut d) in q_des.iter_mut() {
-        d.timer.tick(time.delta());
+        d.timer.tick(clock.delta());
         if d.timer.finished() {
             commands.entity(e).despawn();
         }
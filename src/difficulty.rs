@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+
+use crate::RunTime;
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Difficulty>()
+            .init_resource::<DifficultyDef>()
+            .init_resource::<DifficultyLevel>();
+    }
+}
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Nightmare,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Nightmare,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Nightmare => "Nightmare",
+        }
+    }
+}
+
+/// Keyframes of `(run seconds, value)`, linear in between.
+/// The first and last keys hold before and after, they are the floor and ceiling of the curve.
+#[derive(Debug, Clone)]
+pub struct Curve(pub Vec<(f32, f32)>);
+
+impl Curve {
+    pub fn sample(&self, seconds: f32) -> f32 {
+        let Some(&(first_seconds, first_value)) = self.0.first() else {
+            return 0f32;
+        };
+        if seconds <= first_seconds {
+            return first_value;
+        }
+        for keys in self.0.windows(2) {
+            let ((from_seconds, from), (to_seconds, to)) = (keys[0], keys[1]);
+            if seconds > to_seconds {
+                continue;
+            }
+            if to_seconds <= from_seconds {
+                return to;
+            }
+            return from + (to - from) * (seconds - from_seconds) / (to_seconds - from_seconds);
+        }
+        self.0[self.0.len() - 1].1
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DifficultyCurves {
+    /// Seconds between two spawns of the endless modes.
+    pub spawn_interval: Curve,
+    /// Shots the whole enemy side may fire per second.
+    pub shots_per_second: Curve,
+    pub health_multiplier: Curve,
+    /// Chance for a spawn of the endless modes to be a big AI.
+    pub big_ai_chance: Curve,
+}
+
+impl DifficultyCurves {
+    pub fn sample(&self, seconds: f32) -> DifficultyLevel {
+        DifficultyLevel {
+            spawn_interval: self.spawn_interval.sample(seconds),
+            shots_per_second: self.shots_per_second.sample(seconds),
+            health_multiplier: self.health_multiplier.sample(seconds),
+            big_ai_chance: self.big_ai_chance.sample(seconds),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct DifficultyDef {
+    pub easy: DifficultyCurves,
+    pub normal: DifficultyCurves,
+    pub hard: DifficultyCurves,
+    pub nightmare: DifficultyCurves,
+}
+
+impl DifficultyDef {
+    pub fn curves(&self, difficulty: Difficulty) -> &DifficultyCurves {
+        match difficulty {
+            Difficulty::Easy => &self.easy,
+            Difficulty::Normal => &self.normal,
+            Difficulty::Hard => &self.hard,
+            Difficulty::Nightmare => &self.nightmare,
+        }
+    }
+}
+
+impl Default for DifficultyDef {
+    fn default() -> Self {
+        Self {
+            easy: DifficultyCurves {
+                spawn_interval: Curve(vec![(0f32, 6f32), (60f32, 2.5f32), (180f32, 1f32)]),
+                shots_per_second: Curve(vec![(0f32, 0.6f32), (60f32, 1f32), (600f32, 10f32)]),
+                health_multiplier: Curve(vec![(0f32, 1f32), (300f32, 1.5f32)]),
+                big_ai_chance: Curve(vec![(0f32, 0.05f32), (120f32, 0.2f32), (300f32, 0.35f32)]),
+            },
+            normal: DifficultyCurves {
+                spawn_interval: Curve(vec![
+                    (0f32, 5f32),
+                    (30f32, 2.5f32),
+                    (60f32, 1f32),
+                    (90f32, 0.6f32),
+                    (150f32, 0.4f32),
+                ]),
+                shots_per_second: Curve(vec![(0f32, 1f32), (35f32, 1f32), (875f32, 25f32)]),
+                health_multiplier: Curve(vec![(0f32, 1f32), (120f32, 1.5f32), (300f32, 2.5f32)]),
+                big_ai_chance: Curve(vec![(0f32, 0.1f32), (60f32, 0.3f32), (180f32, 0.5f32)]),
+            },
+            hard: DifficultyCurves {
+                spawn_interval: Curve(vec![
+                    (0f32, 3.5f32),
+                    (30f32, 1.5f32),
+                    (60f32, 0.7f32),
+                    (120f32, 0.35f32),
+                ]),
+                shots_per_second: Curve(vec![(0f32, 1.5f32), (400f32, 25f32)]),
+                health_multiplier: Curve(vec![(0f32, 1.25f32), (120f32, 2f32), (300f32, 3.5f32)]),
+                big_ai_chance: Curve(vec![(0f32, 0.2f32), (60f32, 0.4f32), (180f32, 0.6f32)]),
+            },
+            nightmare: DifficultyCurves {
+                spawn_interval: Curve(vec![(0f32, 2f32), (30f32, 0.8f32), (90f32, 0.25f32)]),
+                shots_per_second: Curve(vec![(0f32, 3f32), (200f32, 30f32)]),
+                health_multiplier: Curve(vec![(0f32, 1.5f32), (120f32, 3f32), (300f32, 5f32)]),
+                big_ai_chance: Curve(vec![(0f32, 0.35f32), (90f32, 0.6f32), (180f32, 0.75f32)]),
+            },
+        }
+    }
+}
+
+/// Values of the selected curves at the current run time.
+#[derive(Resource, Debug)]
+pub struct DifficultyLevel {
+    pub spawn_interval: f32,
+    pub shots_per_second: f32,
+    pub health_multiplier: f32,
+    pub big_ai_chance: f32,
+}
+
+impl FromWorld for DifficultyLevel {
+    fn from_world(world: &mut World) -> Self {
+        let difficulty = *world.resource::<Difficulty>();
+        world
+            .resource::<DifficultyDef>()
+            .curves(difficulty)
+            .sample(0f32)
+    }
+}
+
+pub fn sample_difficulty(
+    run_time: Res<RunTime>,
+    difficulty: Res<Difficulty>,
+    difficulty_def: Res<DifficultyDef>,
+    mut level: ResMut<DifficultyLevel>,
+) {
+    *level = difficulty_def
+        .curves(*difficulty)
+        .sample(run_time.0.elapsed_secs());
+}
@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+
+use crate::{
+    ai::{AggressionBudget, Ai, AiDeathEvent},
+    clock::GameClock,
+    player::{Player, PlayerDamagedEvent},
+    run::RunScopedAppExt,
+    Health,
+};
+
+pub struct DirectorPlugin;
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_run_resource::<AiDirector>();
+    }
+}
+
+/// Lower and upper values the director is allowed to pick for each knob,
+/// the first one is used when the player struggles, the second when they dominate.
+#[derive(Debug, Clone)]
+pub struct DirectorBounds {
+    pub spawn_rate_multiplier: (f32, f32),
+    pub big_ai_chance_multiplier: (f32, f32),
+    pub aggression_multiplier: (f32, f32),
+    pub pickup_drop_chance: (f32, f32),
+    pub health_pickup_share: (f32, f32),
+}
+
+impl Default for DirectorBounds {
+    fn default() -> Self {
+        Self {
+            spawn_rate_multiplier: (0.6f32, 1.6f32),
+            big_ai_chance_multiplier: (0.5f32, 1.5f32),
+            aggression_multiplier: (0.6f32, 1.5f32),
+            pickup_drop_chance: (1f32, 0.55f32),
+            health_pickup_share: (0.65f32, 0.25f32),
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct AiDirector {
+    pub bounds: DirectorBounds,
+    pub evaluation_timer: Timer,
+    /// How quickly the knobs move toward their new target, 1 jumps immediately.
+    pub smoothing: f32,
+
+    // What the director saw during the current window.
+    pub damage_taken: u32,
+    pub kills: u32,
+
+    // What it concluded at the last evaluation.
+    pub damage_per_second: f32,
+    pub kills_per_second: f32,
+    pub intensity: f32,
+
+    pub spawn_rate_multiplier: f32,
+    pub big_ai_chance_multiplier: f32,
+    pub aggression_multiplier: f32,
+    pub pickup_drop_chance: f32,
+    pub health_pickup_share: f32,
+}
+
+impl Default for AiDirector {
+    fn default() -> Self {
+        let bounds = DirectorBounds::default();
+        let mut director = Self {
+            evaluation_timer: Timer::from_seconds(2f32, TimerMode::Repeating),
+            smoothing: 0.35f32,
+            damage_taken: 0,
+            kills: 0,
+            damage_per_second: 0f32,
+            kills_per_second: 0f32,
+            intensity: 0.5f32,
+            spawn_rate_multiplier: 0f32,
+            big_ai_chance_multiplier: 0f32,
+            aggression_multiplier: 0f32,
+            pickup_drop_chance: 0f32,
+            health_pickup_share: 0f32,
+            bounds,
+        };
+        director.apply_intensity(0.5f32, 1f32);
+        director
+    }
+}
+
+impl AiDirector {
+    fn apply_intensity(&mut self, intensity: f32, smoothing: f32) {
+        let pick = |(low, high): (f32, f32), current: f32| {
+            let target = low + (high - low) * intensity;
+            current + (target - current) * smoothing
+        };
+        self.intensity = intensity;
+        self.spawn_rate_multiplier = pick(
+            self.bounds.spawn_rate_multiplier,
+            self.spawn_rate_multiplier,
+        );
+        self.big_ai_chance_multiplier = pick(
+            self.bounds.big_ai_chance_multiplier,
+            self.big_ai_chance_multiplier,
+        );
+        self.aggression_multiplier = pick(
+            self.bounds.aggression_multiplier,
+            self.aggression_multiplier,
+        );
+        self.pickup_drop_chance = pick(self.bounds.pickup_drop_chance, self.pickup_drop_chance);
+        self.health_pickup_share = pick(self.bounds.health_pickup_share, self.health_pickup_share);
+    }
+}
+
+pub fn ai_director(
+    clock: Res<GameClock>,
+    mut director: ResMut<AiDirector>,
+    mut aggression_budget: ResMut<AggressionBudget>,
+    mut damaged_events: EventReader<PlayerDamagedEvent>,
+    mut ai_killed: EventReader<AiDeathEvent>,
+    q_player: Query<&Health, With<Player>>,
+    q_ais: Query<(), With<Ai>>,
+) {
+    director.damage_taken += damaged_events.iter().count() as u32;
+    director.kills += ai_killed.iter().count() as u32;
+
+    director.evaluation_timer.tick(clock.delta());
+    if !director.evaluation_timer.just_finished() {
+        return;
+    }
+    let window = director.evaluation_timer.duration().as_secs_f32();
+    director.damage_per_second = director.damage_taken as f32 / window;
+    director.kills_per_second = director.kills as f32 / window;
+    director.damage_taken = 0;
+    director.kills = 0;
+
+    // In co-op the player in the worst shape sets the pressure
+    let health_ratio = q_player
+        .iter()
+        .map(|health| (health.current / health.max).clamp(0f32, 1f32))
+        .reduce(f32::min)
+        .unwrap_or(1f32);
+    let enemy_count = q_ais.iter().count();
+
+    // Everything is normalized to 0..1 so the weights read as percentages.
+    let pressure = ((1f32 - health_ratio) * 0.5f32
+        + (director.damage_per_second / 0.5f32).min(1f32) * 0.3f32
+        + (enemy_count as f32 / 30f32).min(1f32) * 0.2f32)
+        .clamp(0f32, 1f32);
+    let dominance = (director.kills_per_second / 0.75f32).min(1f32) * health_ratio;
+    let intensity = (0.5f32 + (dominance - pressure) * 0.5f32).clamp(0f32, 1f32);
+
+    let smoothing = director.smoothing;
+    director.apply_intensity(intensity, smoothing);
+    aggression_budget.multiplier = director.aggression_multiplier;
+
+    info!(
+        "director: health {:.2} damage/s {:.2} kills/s {:.2} enemies {} -> intensity {:.2}, spawn rate x{:.2}, big ai x{:.2}, aggression x{:.2}, drops {:.0}% ({:.0}% health)",
+        health_ratio,
+        director.damage_per_second,
+        director.kills_per_second,
+        enemy_count,
+        director.intensity,
+        director.spawn_rate_multiplier,
+        director.big_ai_chance_multiplier,
+        director.aggression_multiplier,
+        director.pickup_drop_chance * 100f32,
+        director.health_pickup_share * 100f32,
+    );
+}
@@ -3,8 +3,17 @@ use std::f32::consts::TAU;
 use bevy::{math::Vec3Swizzles, prelude::*};
 use bevy_vector_shapes::prelude::*;
 
-use crate::ai::BigAi;
+use crate::affixes::{Affix, Affixes, Explosion, Shield};
+use crate::ai::{AiKind, BigAi};
+use crate::dash::{Afterimage, Dash, Invulnerable};
+use crate::despawn_after::DespawnAfter;
+use crate::loot::Rarity;
+use crate::pickups::{PickupDef, PickupLifetime};
+use crate::player::PlayerSlot;
+use crate::powerups::Buffs;
+use crate::spawning::{ActiveSpawnMode, SpawnDef, SpawnMode, SpawnTelegraph};
 use crate::{
+    clock::GameClock,
     movement::{MoveDirection, MoveTarget},
     Cooldown, Health, Pickup, PickupKind, TeamIdx, Teams,
 };
@@ -12,13 +21,40 @@ use crate::{
 pub fn draw(
     teams: Res<Teams>,
     mut gizmos: Gizmos,
-    q_movers: Query<(&Transform, &TeamIdx, Option<&BigAi>), With<MoveTarget>>,
+    q_movers: Query<
+        (
+            &Transform,
+            &TeamIdx,
+            Option<&BigAi>,
+            Option<&Affixes>,
+            Option<&Shield>,
+            Option<&PlayerSlot>,
+        ),
+        With<MoveTarget>,
+    >,
 ) {
-    for (transform, team, big_ai_option) in q_movers.iter() {
+    for (transform, team, big_ai_option, affixes_option, shield_option, slot_option) in
+        q_movers.iter()
+    {
+        let mut radius = 5f32;
         if let Some(_) = big_ai_option {
-            gizmos.circle_2d(transform.translation.xy(), 15f32, teams.colors[team.0].0);
-        } else {
-            gizmos.circle_2d(transform.translation.xy(), 5f32, teams.colors[team.0].0);
+            radius = 15f32;
+        }
+        gizmos.circle_2d(
+            transform.translation.xy(),
+            radius,
+            teams.colors_of(*team, slot_option).0,
+        );
+        let Some(affixes) = affixes_option else {
+            continue;
+        };
+        // One outer ring per affix, a broken shield doesn't show anymore
+        for affix in affixes.0.iter() {
+            if *affix == Affix::Shielded && shield_option.map_or(0, |shield| shield.hits) == 0 {
+                continue;
+            }
+            radius += 3f32;
+            gizmos.circle_2d(transform.translation.xy(), radius, affix.color());
         }
     }
 }
@@ -59,15 +95,15 @@ pub fn draw_health(
     }
 }
 pub fn draw_cooldown(
-    time: Res<Time>,
+    clock: Res<GameClock>,
     mut painter: ShapePainter,
     q_movers: Query<(&Transform, &Cooldown, &TeamIdx, Option<&BigAi>)>,
 ) {
     for (transform, cooldown, team, big_ai_option) in q_movers.iter() {
-        if cooldown.start_time + cooldown.duration < time.elapsed_seconds() {
+        if cooldown.start_time + cooldown.duration < clock.elapsed_seconds() {
             continue;
         }
-        let ratio = (time.elapsed_seconds() - cooldown.start_time) / cooldown.duration;
+        let ratio = (clock.elapsed_seconds() - cooldown.start_time) / cooldown.duration;
         painter.set_translation(transform.translation);
 
         let start_angle = 0f32 * 3.0;
@@ -84,23 +120,167 @@ pub fn draw_cooldown(
     }
 }
 
-pub fn draw_pickups(time: Res<Time>, mut gizmos: Gizmos, q_movers: Query<(&Transform, &Pickup)>) {
-    for (transform, pickup) in q_movers.iter() {
+/// Dash cooldown, drawn outside of the weapon one.
+pub fn draw_dash(
+    clock: Res<GameClock>,
+    mut painter: ShapePainter,
+    q_dashers: Query<(&Transform, &Dash)>,
+) {
+    for (transform, dash) in q_dashers.iter() {
+        if dash.start_time + dash.cooldown < clock.elapsed_seconds() {
+            continue;
+        }
+        let ratio = (clock.elapsed_seconds() - dash.start_time) / dash.cooldown;
+        painter.set_translation(transform.translation);
+        painter.thickness = 1f32;
+        painter.hollow = true;
+        painter.color = Color::CYAN * 2f32;
+        painter.cap = Cap::None;
+        painter.arc(16f32, 0f32, ratio * TAU);
+    }
+}
+
+pub fn draw_afterimages(
+    teams: Res<Teams>,
+    mut gizmos: Gizmos,
+    q_afterimages: Query<(&Transform, &DespawnAfter, &Afterimage)>,
+    q_invulnerable: Query<(&Transform, &TeamIdx, Option<&PlayerSlot>), With<Invulnerable>>,
+) {
+    for (transform, despawn_after, afterimage) in q_afterimages.iter() {
+        let fade = 1f32 - despawn_after.timer.percent();
+        gizmos.circle_2d(
+            transform.translation.xy(),
+            5f32,
+            teams
+                .colors_of(TeamIdx(0), Some(&afterimage.0))
+                .0
+                .with_a(fade * 0.6f32),
+        );
+    }
+    for (transform, team, slot) in q_invulnerable.iter() {
+        gizmos.circle_2d(
+            transform.translation.xy(),
+            8f32,
+            teams.colors_of(*team, slot).1,
+        );
+    }
+}
+
+pub fn draw_pickups(
+    clock: Res<GameClock>,
+    pickup_def: Res<PickupDef>,
+    mut gizmos: Gizmos,
+    q_movers: Query<(&Transform, &Pickup, &Rarity, Option<&PickupLifetime>)>,
+) {
+    for (transform, pickup, rarity, lifetime) in q_movers.iter() {
+        if lifetime.is_some_and(|lifetime| !lifetime.is_visible(pickup_def.expiry_warning_seconds))
+        {
+            continue;
+        }
+        if *rarity != Rarity::Common {
+            gizmos.circle_2d(transform.translation.xy(), 7f32, rarity.color());
+        }
         match pickup.0 {
             PickupKind::Health(_) => {
                 gizmos.circle_2d(
                     transform.translation.xy(),
-                    2f32 + (time.elapsed_seconds() * 3f32).sin(),
+                    2f32 + (clock.elapsed_seconds() * 3f32).sin(),
                     Color::BLUE * 3f32,
                 );
             }
             PickupKind::Weapon(_) => {
                 gizmos.circle_2d(
                     transform.translation.xy(),
-                    2f32 + (time.elapsed_seconds() * 3f32).sin(),
+                    2f32 + (clock.elapsed_seconds() * 3f32).sin(),
                     Color::RED * 3f32,
                 );
             }
+            PickupKind::Vacuum => {
+                gizmos.circle_2d(
+                    transform.translation.xy(),
+                    4f32 + (clock.elapsed_seconds() * 6f32).sin(),
+                    Color::YELLOW * 3f32,
+                );
+            }
+            PickupKind::Bomb => {
+                gizmos.circle_2d(transform.translation.xy(), 4f32, Color::ORANGE_RED * 4f32);
+                gizmos.line_2d(
+                    transform.translation.xy() + Vec2::new(2f32, 3f32),
+                    transform.translation.xy() + Vec2::new(4f32, 7f32),
+                    Color::WHITE,
+                );
+            }
+            PickupKind::Xp(amount) => {
+                gizmos.rect_2d(
+                    transform.translation.xy(),
+                    std::f32::consts::FRAC_PI_4,
+                    Vec2::splat(if amount > 1 { 5f32 } else { 3f32 }),
+                    Color::LIME_GREEN * 3f32,
+                );
+            }
+            PickupKind::PowerUp(power_up) => {
+                gizmos.rect_2d(
+                    transform.translation.xy(),
+                    clock.elapsed_seconds() * 2f32,
+                    Vec2::splat(6f32),
+                    power_up.color(),
+                );
+            }
         }
     }
 }
+
+/// One ring per active power-up around its owner.
+pub fn draw_buffs(mut gizmos: Gizmos, q_buffed: Query<(&Transform, &Buffs)>) {
+    for (transform, buffs) in q_buffed.iter() {
+        for (i, buff) in buffs.0.iter().enumerate() {
+            gizmos.circle_2d(
+                transform.translation.xy(),
+                9f32 + i as f32 * 3f32,
+                buff.power_up.color(),
+            );
+        }
+    }
+}
+
+pub fn draw_explosions(
+    mut gizmos: Gizmos,
+    q_explosions: Query<(&Transform, &Explosion, &DespawnAfter)>,
+) {
+    for (transform, explosion, despawn_after) in q_explosions.iter() {
+        let ratio = despawn_after.timer.percent();
+        gizmos.circle_2d(
+            transform.translation.xy(),
+            explosion.radius * ratio,
+            Color::ORANGE_RED * (5f32 * (1f32 - ratio)),
+        );
+    }
+}
+
+pub fn draw_telegraphs(
+    clock: Res<GameClock>,
+    spawn_def: Res<SpawnDef>,
+    spawn_mode: Res<ActiveSpawnMode>,
+    mut gizmos: Gizmos,
+    q_telegraphs: Query<(&Transform, &SpawnTelegraph)>,
+) {
+    if spawn_mode.0 == SpawnMode::Portal {
+        for portal in spawn_def.portals.iter() {
+            gizmos.circle_2d(*portal, spawn_def.portal_scatter, Color::PURPLE * 2f32);
+        }
+    }
+    for (transform, telegraph) in q_telegraphs.iter() {
+        let radius = match telegraph.kind {
+            AiKind::Small => 5f32,
+            AiKind::Big => 15f32,
+        };
+        // Pulses faster as the spawn gets closer
+        let ratio = telegraph.timer.percent();
+        let pulse = (clock.elapsed_seconds() * (6f32 + ratio * 18f32)).sin() * 0.5f32 + 0.5f32;
+        gizmos.circle_2d(
+            transform.translation.xy(),
+            radius * (1f32 + pulse * 0.5f32),
+            Color::ORANGE * (1f32 + ratio * 4f32),
+        );
+    }
+}
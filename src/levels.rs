@@ -0,0 +1,359 @@
+use bevy::prelude::*;
+
+use crate::{
+    controls::{MenuAction, MenuInput},
+    menu::GameState,
+    player::{Coop, Player, PlayerSlot},
+    rng::RunRng,
+    run::RunScopedAppExt,
+    upgrades::{apply_upgrade, UpgradeKind, UpgradePool, UpgradeTarget, Upgrades},
+};
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelDef>()
+            .init_run_resource::<LevelUp>()
+            .add_systems(Startup, setup_xp_bar)
+            .add_systems(Update, display_xp_bar)
+            .add_systems(OnEnter(GameState::LevelUp), display_level_up)
+            .add_systems(OnExit(GameState::LevelUp), hide_level_up)
+            .add_systems(
+                Update,
+                (highlight_upgrade_buttons, pick_upgrade).run_if(in_state(GameState::LevelUp)),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct LevelDef {
+    /// Every level needs that much more XP than the previous one.
+    pub xp_growth: f32,
+}
+
+impl Default for LevelDef {
+    fn default() -> Self {
+        Self { xp_growth: 1.4f32 }
+    }
+}
+
+/// Filled by XP gems, a level-up is offered when `xp` reaches `next_level`.
+#[derive(Component, Debug)]
+pub struct Experience {
+    pub xp: u32,
+    pub level: u32,
+    pub next_level: u32,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Self {
+            xp: 0,
+            level: 1,
+            next_level: 5,
+        }
+    }
+}
+
+/// The upgrades offered by the level-up being picked.
+#[derive(Resource, Debug, Default)]
+pub struct LevelUp {
+    pub owner: Option<Entity>,
+    pub choices: Vec<UpgradeKind>,
+    /// Choice highlighted with LEFT/RIGHT or the D-pad.
+    pub cursor: Option<usize>,
+}
+
+pub fn check_level_up(
+    level_def: Res<LevelDef>,
+    upgrade_pool: Res<UpgradePool>,
+    mut level_up: ResMut<LevelUp>,
+    mut run_rng: ResMut<RunRng>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut q_players: Query<(Entity, &mut Experience, &Upgrades), With<Player>>,
+) {
+    for (e, mut experience, upgrades) in q_players.iter_mut() {
+        if experience.xp < experience.next_level {
+            continue;
+        }
+        experience.xp -= experience.next_level;
+        experience.level += 1;
+        experience.next_level = (experience.next_level as f32 * level_def.xp_growth).ceil() as u32;
+        let choices = upgrade_pool.roll_choices(upgrades, &mut run_rng.0);
+        // Everything is maxed out, keep playing
+        if choices.is_empty() {
+            continue;
+        }
+        level_up.owner = Some(e);
+        level_up.choices = choices;
+        level_up.cursor = None;
+        game_state.set(GameState::LevelUp);
+        // Extra levels are offered one after the other once back in game
+        return;
+    }
+}
+
+#[derive(Component)]
+struct LevelUpScreen;
+
+#[derive(Component)]
+struct UpgradeButton(usize);
+
+const BUTTON_COLOR: Color = Color::rgba(0.1, 0.1, 0.1, 0.8);
+const BUTTON_HOVERED_COLOR: Color = Color::rgba(0.3, 0.3, 0.3, 0.9);
+
+fn display_level_up(
+    mut commands: Commands,
+    level_up: Res<LevelUp>,
+    upgrade_pool: Res<UpgradePool>,
+    coop: Res<Coop>,
+    q_experience: Query<(&Experience, &PlayerSlot)>,
+) {
+    let (level, slot) = level_up
+        .owner
+        .and_then(|owner| q_experience.get(owner).ok())
+        .map_or((0, 0), |(experience, slot)| (experience.level, slot.0));
+    // Only name the player when there is more than one
+    let who = if coop.0 {
+        format!("P{} ", slot + 1)
+    } else {
+        String::new()
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::GRAY.with_a(0.5)),
+                ..default()
+            },
+            LevelUpScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!(
+                    "{}Level {}! Pick an upgrade (1-{}, click or D-pad and A)",
+                    who,
+                    level,
+                    level_up.choices.len()
+                ),
+                TextStyle {
+                    font_size: 50.,
+                    ..default()
+                },
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(20.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (i, kind) in level_up.choices.iter().enumerate() {
+                        let upgrade = upgrade_pool.get(*kind);
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(260.),
+                                        padding: UiRect::all(Val::Px(15.)),
+                                        flex_direction: FlexDirection::Column,
+                                        ..default()
+                                    },
+                                    background_color: BackgroundColor(BUTTON_COLOR),
+                                    ..default()
+                                },
+                                UpgradeButton(i),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_sections([
+                                    TextSection::new(
+                                        format!("{}. {}\n", i + 1, upgrade.name),
+                                        TextStyle {
+                                            font_size: 32.,
+                                            color: upgrade.rarity.color(),
+                                            ..default()
+                                        },
+                                    ),
+                                    TextSection::new(
+                                        upgrade.description,
+                                        TextStyle {
+                                            font_size: 24.,
+                                            ..default()
+                                        },
+                                    ),
+                                ]));
+                            });
+                    }
+                });
+        });
+}
+
+fn hide_level_up(mut commands: Commands, q_screen: Query<Entity, With<LevelUpScreen>>) {
+    for e in q_screen.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+fn highlight_upgrade_buttons(
+    level_up: Res<LevelUp>,
+    mut q_buttons: Query<(&Interaction, &UpgradeButton, &mut BackgroundColor)>,
+) {
+    for (interaction, button, mut color) in q_buttons.iter_mut() {
+        color.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed => BUTTON_HOVERED_COLOR,
+            Interaction::None if level_up.cursor == Some(button.0) => BUTTON_HOVERED_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        };
+    }
+}
+
+fn pick_upgrade(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    menu_input: MenuInput,
+    q_buttons: Query<(&Interaction, &UpgradeButton), Changed<Interaction>>,
+    mut level_up: ResMut<LevelUp>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut q_targets: Query<UpgradeTarget>,
+) {
+    const KEYS: [KeyCode; 3] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+    let count = level_up.choices.len();
+    if count > 0 {
+        if menu_input.just_pressed(MenuAction::Right) {
+            level_up.cursor = Some(level_up.cursor.map_or(0, |cursor| (cursor + 1) % count));
+        } else if menu_input.just_pressed(MenuAction::Left) {
+            level_up.cursor = Some(
+                level_up
+                    .cursor
+                    .map_or(count - 1, |cursor| (cursor + count - 1) % count),
+            );
+        }
+    }
+    let selected = level_up
+        .cursor
+        .filter(|_| menu_input.just_pressed(MenuAction::Select));
+    let clicked = q_buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| button.0);
+    let Some(picked) = clicked
+        .or_else(|| {
+            KEYS.iter()
+                .position(|key| keyboard_input.just_pressed(*key))
+        })
+        .or(selected)
+        .and_then(|i| level_up.choices.get(i).copied())
+    else {
+        return;
+    };
+    if let Some(mut target) = level_up
+        .owner
+        .and_then(|owner| q_targets.get_mut(owner).ok())
+    {
+        apply_upgrade(&mut commands, &mut target, picked);
+    }
+    level_up.owner = None;
+    level_up.choices.clear();
+    game_state.set(GameState::Playing);
+}
+
+/// One bar per player, the second one only shows in co-op.
+#[derive(Component)]
+struct XpBar(usize);
+
+#[derive(Component)]
+struct XpBarFill(usize);
+
+#[derive(Component)]
+struct XpBarText(usize);
+
+fn setup_xp_bar(mut commands: Commands) {
+    for (slot, color) in [Color::LIME_GREEN, Color::CYAN].into_iter().enumerate() {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(8. + slot as f32 * 36.),
+                        left: Val::Percent(30.),
+                        width: Val::Percent(40.),
+                        height: Val::Px(10.),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::BLACK.with_a(0.6)),
+                    ..default()
+                },
+                XpBar(slot),
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(0.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(color),
+                        ..default()
+                    },
+                    XpBarFill(slot),
+                ));
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 20.,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(12.),
+                        ..default()
+                    }),
+                    XpBarText(slot),
+                ));
+            });
+    }
+}
+
+fn display_xp_bar(
+    coop: Res<Coop>,
+    q_experience: Query<(&Experience, &PlayerSlot)>,
+    mut q_bars: Query<(&XpBar, &mut Style), Without<XpBarFill>>,
+    mut q_fill: Query<(&XpBarFill, &mut Style), Without<XpBar>>,
+    mut q_text: Query<(&XpBarText, &mut Text)>,
+) {
+    for (bar, mut style) in q_bars.iter_mut() {
+        style.display = if bar.0 < coop.players() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    // A player waiting to respawn keeps the last values on their bar
+    for (experience, slot) in q_experience.iter() {
+        for (fill, mut style) in q_fill.iter_mut().filter(|(fill, _)| fill.0 == slot.0) {
+            let ratio = (experience.xp as f32 / experience.next_level as f32).min(1f32);
+            style.width = Val::Percent(ratio * 100.);
+        }
+        for (text, mut text_value) in q_text.iter_mut().filter(|(text, _)| text.0 == slot.0) {
+            text_value.sections[0].value = if coop.0 {
+                format!("P{} Lv {}", slot.0 + 1, experience.level)
+            } else {
+                format!("Lv {}", experience.level)
+            };
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use bevy::{ecs::system::Command, prelude::*};
+
+use crate::{
+    bombs::Bombs,
+    clock::GameClock,
+    controls::{MenuAction, MenuInput},
+    dash::Invulnerable,
+    levels::Experience,
+    menu::GameState,
+    meta::{MetaDef, MetaProgress},
+    modes::{GameMode, ModeDef, RunScore},
+    player::PlayerSlot,
+    powerups::Buffs,
+    run::{LastRun, RunScopedAppExt},
+    spawn_player,
+    stats::{StatSource, Stats},
+    upgrades::{Pierce, Upgrades},
+};
+
+pub struct LivesPlugin;
+
+impl Plugin for LivesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LivesDef>()
+            .init_run_resource::<PendingRespawn>()
+            .init_run_resource::<StashedPlayers>()
+            .add_event::<PlayerDownedEvent>()
+            .add_systems(OnEnter(GameState::GameOver), display_game_over)
+            .add_systems(OnExit(GameState::GameOver), hide_game_over)
+            .add_systems(
+                Update,
+                leave_game_over.run_if(in_state(GameState::GameOver)),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct LivesDef {
+    pub respawn_seconds: f32,
+    pub invulnerable_seconds: f32,
+}
+
+impl Default for LivesDef {
+    fn default() -> Self {
+        Self {
+            respawn_seconds: 1.5f32,
+            invulnerable_seconds: 2f32,
+        }
+    }
+}
+
+/// Counts down for every dead player while lives are left, by `PlayerSlot`.
+#[derive(Resource, Debug, Default)]
+pub struct PendingRespawn(pub HashMap<usize, Timer>);
+
+#[derive(Event)]
+pub struct PlayerDownedEvent(pub PlayerSlot);
+
+/// What a player earned during the run, given back when they respawn.
+#[derive(Default)]
+pub struct StashedPlayer {
+    stats: Option<Stats>,
+    experience: Option<Experience>,
+    upgrades: Option<Upgrades>,
+    bombs: Option<Bombs>,
+    pierce: Option<Pierce>,
+}
+
+#[derive(Resource, Default)]
+pub struct StashedPlayers(HashMap<usize, StashedPlayer>);
+
+/// Despawns a dead player, keeping its run progress in `StashedPlayers`.
+pub struct DownPlayer(pub Entity);
+
+impl Command for DownPlayer {
+    fn apply(self, world: &mut World) {
+        let Some(mut player) = world.get_entity_mut(self.0) else {
+            return;
+        };
+        let slot = player.get::<PlayerSlot>().copied().unwrap_or(PlayerSlot(0));
+        let mut stats = player.take::<Stats>();
+        // Power-ups are lost on death
+        if let (Some(stats), Some(buffs)) = (stats.as_mut(), player.get::<Buffs>()) {
+            for buff in buffs.0.iter() {
+                stats.remove_source(StatSource::PowerUp(buff.power_up));
+            }
+        }
+        let stash = StashedPlayer {
+            stats,
+            experience: player.take::<Experience>(),
+            upgrades: player.take::<Upgrades>(),
+            bombs: player.take::<Bombs>(),
+            pierce: player.take::<Pierce>(),
+        };
+        player.despawn();
+        world
+            .resource_mut::<StashedPlayers>()
+            .0
+            .insert(slot.0, stash);
+        world.send_event(PlayerDownedEvent(slot));
+    }
+}
+
+struct RestorePlayer(Entity, PlayerSlot);
+
+impl Command for RestorePlayer {
+    fn apply(self, world: &mut World) {
+        let Some(stash) = world.resource_mut::<StashedPlayers>().0.remove(&self.1 .0) else {
+            return;
+        };
+        let Some(mut player) = world.get_entity_mut(self.0) else {
+            return;
+        };
+        if let Some(stats) = stash.stats {
+            player.insert(stats);
+        }
+        if let Some(experience) = stash.experience {
+            player.insert(experience);
+        }
+        if let Some(upgrades) = stash.upgrades {
+            player.insert(upgrades);
+        }
+        if let Some(bombs) = stash.bombs {
+            player.insert(bombs);
+        }
+        if let Some(pierce) = stash.pierce {
+            player.insert(pierce);
+        }
+    }
+}
+
+pub fn lives_left(mode: &GameMode, mode_def: &ModeDef, score: &RunScore) -> u32 {
+    mode.lives(mode_def).saturating_sub(score.lives_lost)
+}
+
+/// Takes a life when a player dies, and brings them back at the center after a delay.
+pub fn respawn_player(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    lives_def: Res<LivesDef>,
+    mode: Res<GameMode>,
+    mode_def: Res<ModeDef>,
+    meta: Res<MetaProgress>,
+    meta_def: Res<MetaDef>,
+    mut score: ResMut<RunScore>,
+    mut pending: ResMut<PendingRespawn>,
+    mut downed: EventReader<PlayerDownedEvent>,
+) {
+    if score.end.is_some() {
+        downed.clear();
+        return;
+    }
+    for ev in downed.iter() {
+        score.lives_lost += 1;
+        if lives_left(&mode, &mode_def, &score) > 0 {
+            pending.0.insert(
+                ev.0 .0,
+                Timer::from_seconds(lives_def.respawn_seconds, TimerMode::Once),
+            );
+        }
+    }
+    for timer in pending.0.values_mut() {
+        timer.tick(clock.delta());
+    }
+    let ready = pending
+        .0
+        .iter()
+        .filter(|(_, timer)| timer.finished())
+        .map(|(slot, _)| *slot)
+        .collect::<Vec<_>>();
+    for slot in ready {
+        pending.0.remove(&slot);
+        let slot = PlayerSlot(slot);
+        let player = spawn_player(&mut commands, meta.starting_stats(&meta_def), slot);
+        commands
+            .entity(player)
+            .insert(Invulnerable(Timer::from_seconds(
+                lives_def.invulnerable_seconds,
+                TimerMode::Once,
+            )));
+        commands.add(RestorePlayer(player, slot));
+    }
+}
+
+#[derive(Component)]
+struct GameOverScreen;
+
+fn display_game_over(mut commands: Commands, last_run: Res<LastRun>, meta: Res<MetaProgress>) {
+    let mut value = match last_run.0 {
+        Some(last_run) => format!(
+            "{}\n\n{} ({})\nScore {}\n{} kills in {:.0}s\n",
+            last_run.end.name().to_uppercase(),
+            last_run.mode.name(),
+            last_run.difficulty.name(),
+            last_run.score,
+            last_run.kills,
+            last_run.seconds
+        ),
+        None => "GAME OVER\n".to_string(),
+    };
+    if let Some(earned) = meta.last_earned {
+        value += &format!("+{} coins\n", earned);
+    }
+    value += "\nPress ENTER or START to continue";
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::BLACK.with_a(0.6)),
+                ..default()
+            },
+            GameOverScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    value,
+                    TextStyle {
+                        font_size: 50.,
+                        ..default()
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center),
+            );
+        });
+}
+
+fn hide_game_over(mut commands: Commands, q_screen: Query<Entity, With<GameOverScreen>>) {
+    for e in q_screen.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+fn leave_game_over(menu_input: MenuInput, mut game_state: ResMut<NextState<GameState>>) {
+    if menu_input.just_pressed(MenuAction::Confirm) {
+        game_state.set(GameState::Menu);
+    }
+}
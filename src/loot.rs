@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+use bevy_asset_loader::prelude::{AssetCollection, AssetCollectionApp};
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use crate::{
+    ai::{AiDeathEvent, AiKind},
+    director::AiDirector,
+    meta::{MetaDef, MetaProgress},
+    pickups::{PickupDef, PickupLifetime},
+    rng::RunRng,
+    Pickup, PickupKind, RemoveOnRespawn,
+};
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<LootTables>()
+            .init_asset_loader::<LootTablesLoader>()
+            .init_collection::<LootAssets>();
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct LootAssets {
+    #[asset(path = "loot/default.loot.ron")]
+    pub tables: Handle<LootTables>,
+}
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+}
+
+impl Rarity {
+    pub fn color(&self) -> Color {
+        match self {
+            Rarity::Common => Color::WHITE,
+            Rarity::Uncommon => Color::GREEN * 3f32,
+            Rarity::Rare => Color::CYAN * 4f32,
+            Rarity::Epic => Color::PURPLE * 5f32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LootEntry {
+    pub pickup: PickupKind,
+    pub weight: f32,
+    #[serde(default)]
+    pub rarity: Rarity,
+    /// Inclusive range of pickups dropped when the entry is picked.
+    pub count: (u32, u32),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LootTable {
+    /// Chance for the weighted entries to drop at all, the director scales it.
+    pub chance: f32,
+    /// Weighted picks among `entries` when they drop.
+    pub rolls: u32,
+    pub entries: Vec<LootEntry>,
+    /// Dropped on every death, whatever the rolls.
+    #[serde(default)]
+    pub guaranteed: Vec<LootEntry>,
+    /// Radius around the death position the pickups land in.
+    pub scatter: f32,
+}
+
+impl LootTable {
+    pub fn roll(
+        &self,
+        chance_multiplier: f32,
+        weight: impl Fn(&LootEntry) -> f32,
+        rng: &mut impl Rng,
+    ) -> Vec<(PickupKind, Rarity)> {
+        let mut picked = self.guaranteed.iter().collect::<Vec<_>>();
+        if rng.gen_range(0f32..1f32) < self.chance * chance_multiplier {
+            for _ in 0..self.rolls {
+                if let Ok(entry) = self.entries.choose_weighted(&mut *rng, &weight) {
+                    picked.push(entry);
+                }
+            }
+        }
+        picked
+            .into_iter()
+            .flat_map(|entry| {
+                let count = rng.gen_range(entry.count.0..=entry.count.1.max(entry.count.0));
+                (0..count).map(move |_| (entry.pickup, entry.rarity))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "3f0f7d6e-9a51-4c0b-8d9e-2b6f4e1c7a30"]
+pub struct LootTables {
+    pub tables: HashMap<AiKind, LootTable>,
+}
+
+#[derive(Default)]
+pub struct LootTablesLoader;
+
+impl AssetLoader for LootTablesLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let loot_tables: LootTables = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(loot_tables));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["loot.ron"]
+    }
+}
+
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    pickup_def: &PickupDef,
+    kind: PickupKind,
+    rarity: Rarity,
+    position: Vec2,
+) {
+    commands.spawn((
+        Pickup(kind),
+        rarity,
+        PickupLifetime(Timer::from_seconds(
+            pickup_def.lifetime(kind, rarity),
+            TimerMode::Once,
+        )),
+        Transform::from_translation(position.extend(2f32)),
+        RemoveOnRespawn,
+    ));
+}
+
+pub fn drop_loot(
+    mut commands: Commands,
+    loot_assets: Res<LootAssets>,
+    loot_tables: Res<Assets<LootTables>>,
+    director: Res<AiDirector>,
+    pickup_def: Res<PickupDef>,
+    meta: Res<MetaProgress>,
+    meta_def: Res<MetaDef>,
+    mut run_rng: ResMut<RunRng>,
+    mut ai_killed: EventReader<AiDeathEvent>,
+) {
+    let loot_tables = loot_tables.get(&loot_assets.tables);
+    let rng = &mut run_rng.0;
+    // With equal base weights, health ends up being `health_pickup_share` of the drops.
+    let share = director.health_pickup_share.clamp(0f32, 0.99f32);
+    let health_weight = share / (1f32 - share);
+    let chance_multiplier = director.pickup_drop_chance * meta.pickup_chance_multiplier(&meta_def);
+    for ev in ai_killed.iter() {
+        let Some(table) = loot_tables.and_then(|loot| loot.tables.get(&ev.kind)) else {
+            continue;
+        };
+        let drops = table.roll(
+            chance_multiplier,
+            |entry| match entry.pickup {
+                PickupKind::Health(_) => entry.weight * health_weight,
+                _ => entry.weight,
+            },
+            rng,
+        );
+        for (kind, rarity) in drops {
+            // sqrt keeps the spread even over the disc instead of bunching in the middle
+            let offset = Vec2::from_angle(rng.gen_range(0f32..std::f32::consts::TAU))
+                * table.scatter
+                * rng.gen_range(0f32..1f32).sqrt();
+            spawn_pickup(&mut commands, &pickup_def, kind, rarity, ev.origin + offset);
+        }
+    }
+}
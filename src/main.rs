@@ -1,31 +1,74 @@
 #![allow(clippy::too_many_arguments, clippy::type_complexity)]
 
+pub mod affixes;
 pub mod ai;
+pub mod bombs;
 mod bullets;
+pub mod clock;
+pub mod controls;
+pub mod dash;
 pub mod despawn_after;
+pub mod difficulty;
+pub mod director;
 pub mod draw;
+pub mod levels;
+pub mod lives;
+pub mod loot;
 pub mod menu;
+pub mod meta;
+pub mod modes;
 pub mod movement;
+pub mod pickups;
 pub mod player;
+pub mod powerups;
+pub mod rng;
+pub mod run;
+pub mod spawning;
+pub mod stats;
+pub mod steering;
+pub mod upgrades;
 pub mod utils;
+pub mod waves;
 
 use bevy::{
     core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
     math::vec2,
     prelude::*,
     render::camera::ScalingMode,
+    time::Stopwatch,
 };
 use bevy_asset_loader::prelude::AssetCollectionApp;
 use bevy_vector_shapes::prelude::*;
-use rand::prelude::*;
+use serde::Deserialize;
 
+use affixes::*;
 use ai::*;
+use bombs::*;
 use bullets::*;
+use clock::*;
+use controls::*;
+use dash::*;
 use despawn_after::*;
+use difficulty::*;
+use director::*;
 use draw::*;
+use levels::*;
+use lives::*;
+use loot::*;
 use menu::*;
+use meta::*;
+use modes::*;
 use movement::*;
+use pickups::*;
 use player::*;
+use powerups::*;
+use rng::*;
+use run::*;
+use spawning::*;
+use stats::*;
+use steering::*;
+use upgrades::*;
+use waves::*;
 
 fn main() {
     App::new()
@@ -75,6 +118,17 @@ pub struct TeamIdx(pub usize);
 #[derive(Resource)]
 pub struct Teams {
     pub colors: Vec<(Color, Color)>,
+    /// Players share a team but each gets its own colors, by `PlayerSlot`.
+    pub player_colors: Vec<(Color, Color)>,
+}
+
+impl Teams {
+    pub fn colors_of(&self, team: TeamIdx, slot: Option<&PlayerSlot>) -> (Color, Color) {
+        match slot {
+            Some(slot) => self.player_colors[slot.0 % self.player_colors.len()],
+            None => self.colors[team.0],
+        }
+    }
 }
 
 impl Default for Teams {
@@ -84,75 +138,129 @@ impl Default for Teams {
                 (Color::WHITE * 5f32, Color::GREEN * 5f32),
                 (Color::ORANGE * 5f32, Color::RED * 5f32),
             ],
+            player_colors: vec![
+                (Color::WHITE * 5f32, Color::GREEN * 5f32),
+                (Color::CYAN * 5f32, Color::BLUE * 5f32),
+            ],
         }
     }
 }
 
-#[derive(Clone)]
-enum PickupKind {
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum PickupKind {
     Health(f32),
     Weapon(u16),
+    /// Pulls every pickup toward the player for a moment.
+    Vacuum,
+    PowerUp(PowerUp),
+    Bomb,
+    /// Experience gem, only players collect it.
+    Xp(u32),
 }
 
 #[derive(Component, Clone)]
-pub struct Pickup(PickupKind);
+pub struct Pickup(pub PickupKind);
 
+/// Target, damage and the entity that dealt it, if any.
 #[derive(Event)]
-pub struct EventTryApplyDamages(pub Entity, pub f32);
+pub struct EventTryApplyDamages(pub Entity, pub f32, pub Option<Entity>);
 
-#[derive(Resource)]
-pub struct GameDef {
-    pub spawn_interval: f32,
-    pub initial_spawn_interval: f32,
-    pub spawn_interval_multiplier_per_second: f32,
-}
-
-impl Default for GameDef {
-    fn default() -> Self {
-        Self {
-            spawn_interval: 5f32,
-            initial_spawn_interval: 5f32, // Messy messy
-            spawn_interval_multiplier_per_second: 0.9f32,
-        }
-    }
-}
+/// Time spent playing the current run.
+#[derive(Resource, Default)]
+pub struct RunTime(pub Stopwatch);
 
 impl Plugin for Game {
     fn build(&self, app: &mut App) {
         app.add_plugins(Shape2dPlugin::default());
-        app.add_plugins(BulletPlugin)
+        // Run scoped resources are built from these, they have to exist first
+        app.init_resource::<Teams>().init_resource::<SteeringDef>();
+        app.add_plugins(ClockPlugin)
+            .add_plugins(ControlsPlugin)
+            .add_plugins(RunPlugin)
+            .add_plugins(RngPlugin)
+            .add_plugins(DifficultyPlugin)
+            .add_plugins(BulletPlugin)
             .add_plugins(MenuPlugin)
+            .add_plugins(MetaPlugin)
             .add_plugins(PlayerPlugin)
-            .add_plugins(AiPlugin);
-        app.init_resource::<GameDef>().init_resource::<Teams>();
+            .add_plugins(AiPlugin)
+            .add_plugins(DirectorPlugin)
+            .add_plugins(AffixPlugin)
+            .add_plugins(SpawningPlugin)
+            .add_plugins(LootPlugin)
+            .add_plugins(PickupPlugin)
+            .add_plugins(PowerUpPlugin)
+            .add_plugins(BombPlugin)
+            .add_plugins(DashPlugin)
+            .add_plugins(LivesPlugin)
+            .add_plugins(UpgradePlugin)
+            .add_plugins(LevelPlugin)
+            .add_plugins(WavePlugin)
+            .add_plugins(ModePlugin);
+        app.init_run_resource::<RunTime>();
         app.add_event::<EventBulletSpawn>()
             .add_event::<EventTryApplyDamages>();
         app.add_systems(Startup, setup);
+        app.add_systems(OnExit(GameState::GameOver), reset_run);
         app.add_systems(
             Update,
             (
-                (player_respawn),
+                (
+                    (respawn_player, player_respawn).chain(),
+                    (apply_power_ups, expire_buffs, apply_stats).chain(),
+                    read_player_input,
+                ),
                 (
                     /*handle_mouse_to_move, */ handle_clicks_to_fire,
                     wasd_movement,
+                    (tick_run_time, sample_difficulty).chain(),
+                    trigger_bomb,
+                    trigger_dash,
+                    expire_invulnerability,
                 ),
                 (
                     move_targets,
+                    ai_steering.after(move_targets),
                     move_direction,
-                    spawn_ais,
-                    ai_fire,
-                    ai_move,
-                    update_spawn_interval,
+                    dash_movement,
+                    attract_pickups,
+                    expire_pickups,
+                    // `RunRng` is drawn from in a fixed order, same seed gives the same rolls
+                    (
+                        spawn_ais.run_if(uses_spawn_timer),
+                        run_waves.run_if(uses_waves),
+                        drain_spawn_queue,
+                        hatch_telegraphs,
+                        ai_fire,
+                        ai_move,
+                        ai_dodge,
+                    )
+                        .chain(),
+                    ai_director,
                 ),
                 (try_apply_damages,),
                 (
+                    affix_deaths,
+                    drop_loot,
+                    score_run,
+                    check_run_end.after(score_run),
                     collisions_player_pickups,
+                    check_level_up
+                        .after(collisions_player_pickups)
+                        .after(drop_loot),
                     collisions_bullet_health,
-                    draw,
-                    draw_bullets,
-                    draw_health,
-                    draw_cooldown,
-                    draw_pickups,
+                    (
+                        draw,
+                        draw_bullets,
+                        draw_health,
+                        draw_cooldown,
+                        draw_pickups,
+                        draw_explosions,
+                        draw_telegraphs,
+                        draw_buffs,
+                        draw_dash,
+                        draw_afterimages,
+                    ),
                 ),
             )
                 .chain()
@@ -168,41 +276,47 @@ fn player_respawn(
         Query<Entity, With<RemoveOnRespawn>>,
     )>,
     mut game_state: ResMut<NextState<GameState>>,
+    run_time: Res<RunTime>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    run_seed: Res<RunSeed>,
+    meta: Res<MetaProgress>,
+    meta_def: Res<MetaDef>,
+    coop: Res<Coop>,
+    score: Res<RunScore>,
+    pending: Res<PendingRespawn>,
+    mut run_started: EventWriter<RunStarted>,
+    mut run_ended: EventWriter<RunEnded>,
 ) {
     if q.p0().iter().next().is_some() {
         return;
     }
-    // Remove extra stuff
-    for e in q.p1().iter() {
-        commands.entity(e).despawn();
+    if run_time.0.elapsed_secs() > 0f32 {
+        // `respawn_player` brings players back while lives are left,
+        // in co-op the run goes on as long as someone is waiting to respawn
+        if score.end.is_none() && !pending.0.is_empty() {
+            return;
+        }
+        run_ended.send(RunEnded(RunSummary {
+            mode: *mode,
+            difficulty: *difficulty,
+            end: score.end.unwrap_or(RunEnd::Died),
+            score: score.score,
+            kills: score.kills,
+            seconds: run_time.0.elapsed_secs(),
+            seed: run_seed.seed,
+        }));
+        // The arena is reset by `reset_run` once the game over screen is left
+        game_state.0 = Some(GameState::GameOver);
+        return;
     }
-    // Spawn player
-    commands.spawn((
-        Transform {
-            translation: Vec2::ZERO.extend(2f32),
-            ..default()
-        },
-        MoveSpeed(130f32),
-        MoveDirection(Vec2::ZERO),
-        MoveTarget {
-            target: Some(Vec2::new(0f32, 0f32)),
-        },
-        Health {
-            current: 1.5f32,
-            max: 1.5f32,
-        },
-        Weapon {
-            bullets: 1u16,
-            max: 36u16,
-            spread: 15_f32,
-        },
-        Cooldown {
-            start_time: 0.0,
-            duration: 0.5,
-        },
-        Player,
-        TeamIdx(0),
-    ));
+    reset_arena(
+        &mut commands,
+        q.p1().iter(),
+        meta.starting_stats(&meta_def),
+        coop.players(),
+        &mut run_started,
+    );
     // Go back to menu
     // This system is called at the begining of the game and triggers the menu,
     // The game should be started in the Playing state to avoid having a double menu
@@ -210,6 +324,84 @@ fn player_respawn(
     game_state.0 = Some(GameState::Menu);
 }
 
+fn reset_run(
+    mut commands: Commands,
+    q_removed: Query<Entity, With<RemoveOnRespawn>>,
+    meta: Res<MetaProgress>,
+    meta_def: Res<MetaDef>,
+    coop: Res<Coop>,
+    mut run_started: EventWriter<RunStarted>,
+) {
+    reset_arena(
+        &mut commands,
+        q_removed.iter(),
+        meta.starting_stats(&meta_def),
+        coop.players(),
+        &mut run_started,
+    );
+}
+
+/// Removes what is left of the last run and spawns the players of the next one.
+fn reset_arena(
+    commands: &mut Commands,
+    removed: impl Iterator<Item = Entity>,
+    starting: StartingStats,
+    players: usize,
+    run_started: &mut EventWriter<RunStarted>,
+) {
+    for e in removed {
+        commands.entity(e).despawn();
+    }
+    for slot in 0..players {
+        spawn_player(commands, starting, PlayerSlot(slot));
+    }
+    run_started.send_default();
+}
+
+fn spawn_player(commands: &mut Commands, starting: StartingStats, slot: PlayerSlot) -> Entity {
+    let dash = Dash::default();
+    let magnet = Magnet { radius: 60f32 };
+    let cooldown = 0.5f32;
+    let controls = match slot.0 {
+        0 => Controls::KeyboardMouse,
+        _ => Controls::Alternate,
+    };
+    let position = Vec2::new(slot.0 as f32 * 30f32, 0f32);
+    let stats = Stats::default()
+        .with_base(Stat::MaxHealth, starting.health)
+        .with_base(Stat::Bullets, starting.bullets as f32)
+        .with_base(Stat::FireCooldown, cooldown)
+        .with_base(Stat::MoveSpeed, starting.move_speed)
+        .with_base(Stat::MagnetRadius, magnet.radius)
+        .with_base(Stat::DashDistance, dash.distance)
+        .with_base(Stat::DashCooldown, dash.cooldown);
+    commands
+        .spawn((
+            Transform {
+                translation: position.extend(2f32),
+                ..default()
+            },
+            stats.move_speed(),
+            MoveDirection(Vec2::ZERO),
+            magnet,
+            Buffs::default(),
+            Bombs { count: 1, max: 3 },
+            dash,
+            (slot, controls, PlayerInput::default()),
+            MoveTarget {
+                target: Some(position),
+            },
+            stats.health(),
+            stats.weapon(36u16, 15_f32),
+            stats.cooldown(),
+            Player,
+            TeamIdx(0),
+            // Grows during the run, last since the components above are built from it
+            (stats, Experience::default(), Upgrades::default()),
+        ))
+        .id()
+}
+
 pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn((
         Camera2dBundle {
@@ -241,19 +433,58 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     });
 }
 
+fn tick_run_time(clock: Res<GameClock>, mut run_time: ResMut<RunTime>) {
+    run_time.0.tick(clock.delta());
+}
+
 pub fn collisions_bullet_health(
     mut commands: Commands,
+    power_up_def: Res<PowerUpDef>,
     mut events_try_damage: EventWriter<EventTryApplyDamages>,
-    q_bullets: Query<(Entity, &Transform, &BulletOwner)>,
-    q_health: Query<(Entity, &Transform, &Health)>,
+    mut q_bullets: Query<(
+        Entity,
+        &Transform,
+        &BulletOwner,
+        &TeamIdx,
+        Option<&mut Piercing>,
+    )>,
+    q_health: Query<(
+        Entity,
+        &Transform,
+        &Health,
+        Option<&TeamIdx>,
+        Option<&Player>,
+    )>,
+    q_buffs: Query<&Buffs>,
 ) {
-    for (e_bullet, bullet_position, bullet_owner) in q_bullets.iter() {
-        for (e, t, _) in q_health.iter() {
+    for (e_bullet, bullet_position, bullet_owner, bullet_team, mut piercing) in q_bullets.iter_mut()
+    {
+        for (e, t, _, option_team, option_player) in q_health.iter() {
+            // No friendly fire between co-op players
+            let teammate =
+                option_player.is_some() && option_team.is_some_and(|team| team.0 == bullet_team.0);
             if bullet_owner.entity != e
+                && !teammate
                 && bullet_position.translation.distance(t.translation) < 20f32
             {
-                commands.entity(e_bullet).despawn();
-                events_try_damage.send(EventTryApplyDamages(e, 0.25f32));
+                match piercing.as_mut() {
+                    Some(piercing) if piercing.hit.contains(&e) => continue,
+                    Some(piercing) if piercing.remaining > 0 => {
+                        piercing.remaining -= 1;
+                        piercing.hit.push(e);
+                    }
+                    _ => {
+                        commands.entity(e_bullet).despawn();
+                    }
+                }
+                let mut damage = 0.25f32;
+                if q_buffs
+                    .get(bullet_owner.entity)
+                    .is_ok_and(|buffs| buffs.is_active(PowerUp::DoubleDamage))
+                {
+                    damage *= power_up_def.damage_multiplier;
+                }
+                events_try_damage.send(EventTryApplyDamages(e, damage, Some(bullet_owner.entity)));
                 continue;
             }
         }
@@ -261,23 +492,39 @@ pub fn collisions_bullet_health(
 }
 pub fn collisions_player_pickups(
     mut commands: Commands,
+    pickup_def: Res<PickupDef>,
     q_pickups: Query<(Entity, &Transform, &Pickup)>,
     mut q_stats: Query<
         (
             Entity,
             &Transform,
             &mut Health,
-            &mut Weapon,
+            &mut Stats,
             Option<&Player>,
+            Option<&mut Bombs>,
+            Option<&mut Experience>,
+            Option<&Weapon>,
         ),
         Without<Pickup>,
     >,
     mut health_events: EventWriter<PlayerPickupHealthEvent>,
     mut weapon_events: EventWriter<PlayerPickupWeaponEvent>,
+    mut power_up_events: EventWriter<PowerUpPickupEvent>,
+    mut bomb_events: EventWriter<BombPickupEvent>,
 ) {
-    for (e, t, mut health, mut weapon, option_player) in q_stats.iter_mut() {
+    for (
+        e,
+        t,
+        mut health,
+        mut stats,
+        option_player,
+        mut option_bombs,
+        mut option_experience,
+        option_weapon,
+    ) in q_stats.iter_mut()
+    {
         for (e_pickup, bullet_position, pickup) in q_pickups.iter() {
-            if bullet_position.translation.distance(t.translation) < 20f32 {
+            if bullet_position.translation.distance(t.translation) < pickup_def.collect_radius {
                 let is_player_picking_up = option_player.is_some();
                 match pickup.0 {
                     PickupKind::Health(health_value) => {
@@ -290,14 +537,59 @@ pub fn collisions_player_pickups(
                         continue;
                     }
                     PickupKind::Weapon(bullet_increase) => {
-                        weapon.bullets += bullet_increase;
-                        weapon.bullets = weapon.bullets.min(weapon.max);
+                        // Pickups share one modifier, capped at what the weapon can fire
+                        stats.grow_modifier(
+                            StatSource::Pickup,
+                            Stat::Bullets,
+                            bullet_increase as f32,
+                            option_weapon.map_or(f32::MAX, |weapon| weapon.max as f32),
+                        );
                         commands.entity(e_pickup).despawn();
                         if is_player_picking_up {
                             weapon_events.send_default();
                         }
                         continue;
                     }
+                    PickupKind::Vacuum => {
+                        if !is_player_picking_up {
+                            continue;
+                        }
+                        commands.entity(e).insert(Vacuum(Timer::from_seconds(
+                            pickup_def.vacuum_seconds,
+                            TimerMode::Once,
+                        )));
+                        commands.entity(e_pickup).despawn();
+                        continue;
+                    }
+                    PickupKind::PowerUp(power_up) => {
+                        if !is_player_picking_up {
+                            continue;
+                        }
+                        power_up_events.send(PowerUpPickupEvent(e, power_up));
+                        commands.entity(e_pickup).despawn();
+                        continue;
+                    }
+                    PickupKind::Bomb => {
+                        // Stays on the ground while the player can't carry more
+                        let Some(bombs) = option_bombs.as_mut() else {
+                            continue;
+                        };
+                        if bombs.count >= bombs.max {
+                            continue;
+                        }
+                        bombs.count += 1;
+                        bomb_events.send_default();
+                        commands.entity(e_pickup).despawn();
+                        continue;
+                    }
+                    PickupKind::Xp(amount) => {
+                        let Some(experience) = option_experience.as_mut() else {
+                            continue;
+                        };
+                        experience.xp += amount;
+                        commands.entity(e_pickup).despawn();
+                        continue;
+                    }
                 }
             }
         }
@@ -313,64 +605,82 @@ pub fn try_apply_damages(
         &mut Health,
         Option<&BigAi>,
         Option<&Player>,
+        Option<&Affixes>,
+        Option<&mut Shield>,
+        Option<&Invulnerable>,
     )>,
     mut player_damage_events: EventWriter<PlayerDamagedEvent>,
     mut ai_killed: EventWriter<AiDeathEvent>,
+    affix_def: Res<AffixDef>,
 ) {
     let mut deleted_entities = Vec::new();
+    let mut damage_dealers = Vec::new();
     for ev in events_try_damage.iter() {
         if deleted_entities.contains(&ev.0) {
             continue;
         }
         match q_health.get_mut(ev.0) {
-            Ok((e, transform, mut health, option_big_ai, option_player)) => {
+            Ok((
+                e,
+                transform,
+                mut health,
+                option_big_ai,
+                option_player,
+                option_affixes,
+                option_shield,
+                option_invulnerable,
+            )) => {
+                if option_invulnerable.is_some() {
+                    continue;
+                }
+                if let Some(mut shield) = option_shield {
+                    if shield.hits > 0 {
+                        shield.hits -= 1;
+                        continue;
+                    }
+                }
                 if let Some(_) = option_player {
                     player_damage_events.send_default();
                 }
-                health.current -= 0.25f32;
+                health.current -= ev.1;
+                if let Some(source) = ev.2 {
+                    damage_dealers.push(source);
+                }
                 // TODO: fire event touched to spawn particles!
                 if dbg!(health.current) <= 0f32 {
                     if let Some(_) = option_player {
                     } else {
                         ai_killed.send(AiDeathEvent {
                             origin: transform.translation.truncate(),
+                            kind: match option_big_ai {
+                                Some(_) => AiKind::Big,
+                                None => AiKind::Small,
+                            },
+                            affixes: option_affixes
+                                .map(|affixes| affixes.0.clone())
+                                .unwrap_or_default(),
                         });
                     }
-                    commands.entity(e).despawn();
-                    deleted_entities.push(ev.0);
-                    let chance = thread_rng().gen_range(1..=100);
-                    let amount_amount_to_spawn = match option_big_ai {
-                        None => 1,
-                        Some(_) => 3,
-                    };
-                    if chance > 60 {
-                        for i in 0..amount_amount_to_spawn {
-                            commands.spawn((
-                                Pickup(PickupKind::Health(0.25f32)),
-                                Transform::from_translation(Vec3::new(
-                                    transform.translation.x + (i * 2) as f32,
-                                    transform.translation.y + (i * 2) as f32,
-                                    transform.translation.z,
-                                )),
-                                RemoveOnRespawn,
-                            ));
-                        }
+                    if option_player.is_some() {
+                        commands.add(DownPlayer(e));
                     } else {
-                        for i in 0..amount_amount_to_spawn {
-                            commands.spawn((
-                                Pickup(PickupKind::Weapon(1)),
-                                Transform::from_translation(Vec3::new(
-                                    transform.translation.x + (i * 2) as f32,
-                                    transform.translation.y + (i * 2) as f32,
-                                    transform.translation.z,
-                                )),
-                                RemoveOnRespawn,
-                            ));
-                        }
+                        commands.entity(e).despawn();
                     }
+                    deleted_entities.push(ev.0);
                 }
             }
             _ => {}
         }
     }
+    // Vampiric AIs heal from every hit that went through
+    for source in damage_dealers {
+        if deleted_entities.contains(&source) {
+            continue;
+        }
+        if let Ok((_, _, mut health, _, _, Some(affixes), _, _)) = q_health.get_mut(source) {
+            if affixes.0.contains(&Affix::Vampiric) {
+                health.current = (health.current + affix_def.vampiric_heal).min(health.max);
+            }
+        }
+    }
 }
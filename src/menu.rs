@@ -1,11 +1,25 @@
 use bevy::{prelude::*, time::Stopwatch};
 
+use crate::{
+    controls::{MenuAction, MenuInput},
+    difficulty::Difficulty,
+    meta::{MetaDef, MetaProgress, MetaUpgrade},
+    modes::{GameMode, ModeDef},
+    player::{Coop, Player, PlayerSlot},
+    rng::{RunRng, RunSeed, SeedSource},
+    run::LastRun,
+    spawn_player,
+    stats::{Stat, Stats},
+    RunTime,
+};
+
 pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<GameState>()
             .init_resource::<LastActivity>()
+            .init_resource::<ShopCursor>()
             .add_systems(Startup, (setup_controls_hint, setup_menu))
             .add_systems(OnEnter(GameState::Menu), display_menu)
             .add_systems(OnExit(GameState::Menu), hide_menu)
@@ -14,6 +28,13 @@ impl Plugin for MenuPlugin {
                 (
                     display_controls_hint,
                     resume_game.run_if(in_state(GameState::Menu)),
+                    select_game_mode.run_if(in_state(GameState::Menu)),
+                    select_difficulty.run_if(in_state(GameState::Menu)),
+                    select_seed.run_if(in_state(GameState::Menu)),
+                    select_coop.run_if(in_state(GameState::Menu)),
+                    display_mode_menu.run_if(in_state(GameState::Menu)),
+                    buy_meta_upgrade.run_if(in_state(GameState::Menu)),
+                    display_shop.run_if(in_state(GameState::Menu)),
                     pause_game.run_if(in_state(GameState::Playing)),
                 ),
             );
@@ -25,6 +46,10 @@ pub enum GameState {
     Menu,
     #[default]
     Playing,
+    /// Paused while an upgrade is picked.
+    LevelUp,
+    /// The run is over, the arena is reset when leaving it.
+    GameOver,
 }
 
 #[derive(Resource, Default)]
@@ -36,12 +61,22 @@ struct ControlsHint;
 #[derive(Component)]
 struct MenuNode;
 
+#[derive(Component)]
+struct ModeMenuText;
+
+#[derive(Component)]
+struct ShopMenuText;
+
+/// Shop item selected in the menu.
+#[derive(Resource, Default)]
+struct ShopCursor(usize);
+
 fn setup_controls_hint(mut commands: Commands) {
     commands.spawn((
         TextBundle {
             text: Text {
                 sections: vec![TextSection {
-                    value: "WASD/Arrows: Move\nMouse/Click: Aim and shoot/Enter: Pause\n"
+                    value: "WASD/Arrows: Move\nMouse/Click: Aim and shoot/Enter: Pause\nQ: Bomb\nSpace/Right click: Dash\nP2: IJKL: Move\nU: Shoot\nO: Dash\nP: Bomb\nGamepad: Left stick: Move\nRight stick: Aim and shoot/Start: Pause\nRB: Dash\nLB: Bomb\n"
                         .to_string(),
                     style: TextStyle {
                         font_size: 30.,
@@ -88,7 +123,7 @@ fn setup_menu(mut commands: Commands) {
         .spawn((TextBundle {
             text: Text {
                 sections: vec![TextSection {
-                    value: "Press ENTER to start/resume the game\n".to_string(),
+                    value: "Press ENTER or START to start/resume the game\n".to_string(),
                     style: TextStyle {
                         font_size: 50.,
                         ..default()
@@ -101,7 +136,35 @@ fn setup_menu(mut commands: Commands) {
         },))
         .id();
 
-    commands.entity(menu_node).add_child(text_node);
+    let mode_node = commands
+        .spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 30.,
+                    ..default()
+                },
+            ),
+            ModeMenuText,
+        ))
+        .id();
+
+    let shop_node = commands
+        .spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 30.,
+                    ..default()
+                },
+            ),
+            ShopMenuText,
+        ))
+        .id();
+
+    commands
+        .entity(menu_node)
+        .push_children(&[text_node, mode_node, shop_node]);
 }
 
 fn display_controls_hint(
@@ -132,18 +195,234 @@ fn hide_menu(mut menu: Query<&mut Style, With<MenuNode>>) {
 }
 
 fn resume_game(
-    keyboard_input: Res<Input<KeyCode>>,
+    menu_input: MenuInput,
     mut game_state: ResMut<NextState<GameState>>,
     mut last_activity: ResMut<LastActivity>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Return) {
+    if menu_input.just_pressed(MenuAction::Confirm) {
         game_state.0 = Some(GameState::Playing);
         last_activity.0.reset();
     }
 }
 
-fn pause_game(keyboard_input: Res<Input<KeyCode>>, mut game_state: ResMut<NextState<GameState>>) {
-    if keyboard_input.just_pressed(KeyCode::Return) {
+fn pause_game(menu_input: MenuInput, mut game_state: ResMut<NextState<GameState>>) {
+    if menu_input.just_pressed(MenuAction::Confirm) {
         game_state.0 = Some(GameState::Menu);
     }
 }
+
+fn select_game_mode(menu_input: MenuInput, run_time: Res<RunTime>, mut mode: ResMut<GameMode>) {
+    // The mode can only change between runs
+    if run_time.0.elapsed_secs() > 0f32 {
+        return;
+    }
+    let index = GameMode::ALL.iter().position(|m| m == &*mode).unwrap_or(0);
+    let count = GameMode::ALL.len();
+    let index = if menu_input.just_pressed(MenuAction::Right) {
+        (index + 1) % count
+    } else if menu_input.just_pressed(MenuAction::Left) {
+        (index + count - 1) % count
+    } else {
+        return;
+    };
+    *mode = GameMode::ALL[index];
+}
+
+fn select_difficulty(
+    menu_input: MenuInput,
+    run_time: Res<RunTime>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    if run_time.0.elapsed_secs() > 0f32 || !menu_input.just_pressed(MenuAction::Difficulty) {
+        return;
+    }
+    let index = Difficulty::ALL
+        .iter()
+        .position(|d| d == &*difficulty)
+        .unwrap_or(0);
+    *difficulty = Difficulty::ALL[(index + 1) % Difficulty::ALL.len()];
+}
+
+/// UP/DOWN picks where the seed comes from, digits and BACKSPACE edit a custom one.
+/// Switching to a custom seed starts from the one of the last run, so it can be played again.
+fn select_seed(
+    keyboard_input: Res<Input<KeyCode>>,
+    menu_input: MenuInput,
+    mut characters: EventReader<ReceivedCharacter>,
+    run_time: Res<RunTime>,
+    last_run: Res<LastRun>,
+    mut run_seed: ResMut<RunSeed>,
+    mut run_rng: ResMut<RunRng>,
+) {
+    let typed = characters
+        .iter()
+        .filter_map(|ev| ev.char.to_digit(10))
+        .collect::<Vec<_>>();
+    // Same as the mode, the seed is picked before the run
+    if run_time.0.elapsed_secs() > 0f32 {
+        return;
+    }
+    let up = menu_input.just_pressed(MenuAction::Up);
+    let down = menu_input.just_pressed(MenuAction::Down);
+    // The current seed was already rolled for the next run
+    let last_seed = last_run.0.map_or(run_seed.seed, |last_run| last_run.seed);
+    let mut source = match run_seed.source {
+        SeedSource::Random if down => SeedSource::Daily,
+        SeedSource::Random if up => SeedSource::Custom(last_seed),
+        SeedSource::Daily if down => SeedSource::Custom(last_seed),
+        SeedSource::Daily if up => SeedSource::Random,
+        SeedSource::Custom(_) if down => SeedSource::Random,
+        SeedSource::Custom(_) if up => SeedSource::Daily,
+        source => source,
+    };
+    if let SeedSource::Custom(seed) = &mut source {
+        for digit in typed {
+            *seed = seed.saturating_mul(10).saturating_add(digit as u64);
+        }
+        if keyboard_input.just_pressed(KeyCode::Back) {
+            *seed /= 10;
+        }
+    }
+    if source != run_seed.source {
+        run_seed.source = source;
+        run_rng.reseed(&mut run_seed);
+    }
+}
+
+/// C switches between one and two players, the second one joins the arena right away.
+fn select_coop(
+    mut commands: Commands,
+    menu_input: MenuInput,
+    run_time: Res<RunTime>,
+    meta: Res<MetaProgress>,
+    meta_def: Res<MetaDef>,
+    mut coop: ResMut<Coop>,
+    q_players: Query<(Entity, &PlayerSlot)>,
+) {
+    if run_time.0.elapsed_secs() > 0f32 || !menu_input.just_pressed(MenuAction::Coop) {
+        return;
+    }
+    coop.0 = !coop.0;
+    if coop.0 {
+        spawn_player(&mut commands, meta.starting_stats(&meta_def), PlayerSlot(1));
+    } else {
+        for (e, _) in q_players.iter().filter(|(_, slot)| slot.0 > 0) {
+            commands.entity(e).despawn();
+        }
+    }
+}
+
+fn display_mode_menu(
+    mode: Res<GameMode>,
+    mode_def: Res<ModeDef>,
+    difficulty: Res<Difficulty>,
+    run_seed: Res<RunSeed>,
+    coop: Res<Coop>,
+    run_time: Res<RunTime>,
+    last_run: Res<LastRun>,
+    mut q_text: Query<&mut Text, With<ModeMenuText>>,
+) {
+    let Ok(mut text) = q_text.get_single_mut() else {
+        return;
+    };
+    let mut value = if run_time.0.elapsed_secs() > 0f32 {
+        format!("Mode: {}\n", mode.name())
+    } else {
+        format!("Mode (LEFT/RIGHT or D-PAD): < {} >\n", mode.name())
+    };
+    value += &mode.description(&mode_def);
+    value += &if run_time.0.elapsed_secs() > 0f32 {
+        format!(
+            "\nDifficulty: {}\nSeed: {}\nPlayers: {}",
+            difficulty.name(),
+            run_seed.seed,
+            coop.players()
+        )
+    } else {
+        format!(
+            "\nDifficulty (D or X): {}\nSeed (UP/DOWN or D-PAD): < {} > {}\nPlayers (C or Y): {}",
+            difficulty.name(),
+            run_seed.source.name(),
+            run_seed.seed,
+            coop.players()
+        )
+    };
+    if let Some(last_run) = last_run.0 {
+        value += &format!(
+            "\n\nLast run ({}, {}, {}): score {}, {} kills in {:.0}s, seed {}",
+            last_run.mode.name(),
+            last_run.difficulty.name(),
+            last_run.end.name(),
+            last_run.score,
+            last_run.kills,
+            last_run.seconds,
+            last_run.seed
+        );
+    }
+    text.sections[0].value = value;
+}
+
+/// TAB or RB picks a shop item, B or A buys its next rank.
+fn buy_meta_upgrade(
+    menu_input: MenuInput,
+    run_time: Res<RunTime>,
+    meta_def: Res<MetaDef>,
+    mut meta: ResMut<MetaProgress>,
+    mut cursor: ResMut<ShopCursor>,
+    mut q_player: Query<&mut Stats, With<Player>>,
+) {
+    if menu_input.just_pressed(MenuAction::ShopNext) {
+        cursor.0 = (cursor.0 + 1) % MetaUpgrade::ALL.len();
+    }
+    // Starting stats are only changed between runs
+    if run_time.0.elapsed_secs() > 0f32 || !menu_input.just_pressed(MenuAction::Buy) {
+        return;
+    }
+    if !meta.buy(&meta_def, MetaUpgrade::ALL[cursor.0]) {
+        return;
+    }
+    // The player of the next run is already waiting in the arena
+    let stats = meta.starting_stats(&meta_def);
+    for mut player_stats in q_player.iter_mut() {
+        player_stats.set_base(Stat::MaxHealth, stats.health);
+        player_stats.set_base(Stat::Bullets, stats.bullets as f32);
+        player_stats.set_base(Stat::MoveSpeed, stats.move_speed);
+    }
+}
+
+fn display_shop(
+    run_time: Res<RunTime>,
+    meta_def: Res<MetaDef>,
+    meta: Res<MetaProgress>,
+    cursor: Res<ShopCursor>,
+    mut q_text: Query<&mut Text, With<ShopMenuText>>,
+) {
+    let Ok(mut text) = q_text.get_single_mut() else {
+        return;
+    };
+    let mut value = format!("\n\nCoins: {}", meta.coins);
+    if let Some(earned) = meta.last_earned {
+        value += &format!(" (+{} last run)", earned);
+    }
+    value += if run_time.0.elapsed_secs() > 0f32 {
+        "\nShop (between runs):"
+    } else {
+        "\nShop (TAB or RB select, B or A buy):"
+    };
+    for (i, upgrade) in MetaUpgrade::ALL.iter().enumerate() {
+        let cursor = if i == cursor.0 { ">" } else { " " };
+        let price = match meta.next_cost(&meta_def, *upgrade) {
+            Some(cost) => format!("{} coins", cost),
+            None => "maxed".to_string(),
+        };
+        value += &format!(
+            "\n{} {} {}/{} - {}",
+            cursor,
+            upgrade.name(),
+            meta.rank(*upgrade),
+            meta_def.max_rank,
+            price
+        );
+    }
+    text.sections[0].value = value;
+}
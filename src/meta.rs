@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::run::RunEnded;
+
+pub struct MetaPlugin;
+
+impl Plugin for MetaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MetaDef>()
+            .insert_resource(MetaProgress::load())
+            .add_systems(Last, award_coins);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_PATH: &str = "circles_madness_save.ron";
+
+/// Permanent upgrades bought in the menu shop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MetaUpgrade {
+    StartingHealth,
+    StartingBullets,
+    PickupChance,
+    MoveSpeed,
+}
+
+impl MetaUpgrade {
+    pub const ALL: [MetaUpgrade; 4] = [
+        MetaUpgrade::StartingHealth,
+        MetaUpgrade::StartingBullets,
+        MetaUpgrade::PickupChance,
+        MetaUpgrade::MoveSpeed,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MetaUpgrade::StartingHealth => "Starting health",
+            MetaUpgrade::StartingBullets => "Starting bullets",
+            MetaUpgrade::PickupChance => "Pickup chance",
+            MetaUpgrade::MoveSpeed => "Move speed",
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct MetaDef {
+    pub coins_per_kill: f32,
+    pub coins_per_minute: f32,
+    /// Price of the first rank, every rank costs `cost_growth` times more.
+    pub base_cost: u32,
+    pub cost_growth: f32,
+    pub max_rank: u32,
+    pub base_health: f32,
+    pub base_bullets: u16,
+    pub base_move_speed: f32,
+    pub health_per_rank: f32,
+    pub bullets_per_rank: u16,
+    pub pickup_chance_per_rank: f32,
+    pub move_speed_per_rank: f32,
+}
+
+impl Default for MetaDef {
+    fn default() -> Self {
+        Self {
+            coins_per_kill: 0.5f32,
+            coins_per_minute: 10f32,
+            base_cost: 20,
+            cost_growth: 1.6f32,
+            max_rank: 5,
+            base_health: 1.5f32,
+            base_bullets: 1,
+            base_move_speed: 130f32,
+            health_per_rank: 0.25f32,
+            bullets_per_rank: 1,
+            pickup_chance_per_rank: 0.1f32,
+            move_speed_per_rank: 0.05f32,
+        }
+    }
+}
+
+impl MetaDef {
+    pub fn cost(&self, rank: u32) -> u32 {
+        (self.base_cost as f32 * self.cost_growth.powi(rank as i32)).round() as u32
+    }
+
+    pub fn coins_for(&self, kills: u32, seconds: f32) -> u32 {
+        (kills as f32 * self.coins_per_kill + seconds / 60f32 * self.coins_per_minute) as u32
+    }
+}
+
+/// What the player starts a run with, before any level-up.
+#[derive(Debug, Clone, Copy)]
+pub struct StartingStats {
+    pub health: f32,
+    pub bullets: u16,
+    pub move_speed: f32,
+}
+
+/// Coins and shop ranks, kept between sessions.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct MetaProgress {
+    pub coins: u32,
+    #[serde(default)]
+    pub ranks: HashMap<MetaUpgrade, u32>,
+    /// Coins earned by the last run, only shown in the menu.
+    #[serde(skip)]
+    pub last_earned: Option<u32>,
+}
+
+impl MetaProgress {
+    pub fn rank(&self, upgrade: MetaUpgrade) -> u32 {
+        self.ranks.get(&upgrade).copied().unwrap_or(0)
+    }
+
+    /// The price of the next rank, none once maxed out.
+    pub fn next_cost(&self, meta_def: &MetaDef, upgrade: MetaUpgrade) -> Option<u32> {
+        let rank = self.rank(upgrade);
+        (rank < meta_def.max_rank).then(|| meta_def.cost(rank))
+    }
+
+    pub fn buy(&mut self, meta_def: &MetaDef, upgrade: MetaUpgrade) -> bool {
+        let Some(cost) = self.next_cost(meta_def, upgrade) else {
+            return false;
+        };
+        if self.coins < cost {
+            return false;
+        }
+        self.coins -= cost;
+        *self.ranks.entry(upgrade).or_insert(0) += 1;
+        self.save();
+        true
+    }
+
+    pub fn starting_stats(&self, meta_def: &MetaDef) -> StartingStats {
+        StartingStats {
+            health: meta_def.base_health
+                + meta_def.health_per_rank * self.rank(MetaUpgrade::StartingHealth) as f32,
+            bullets: meta_def.base_bullets
+                + meta_def.bullets_per_rank * self.rank(MetaUpgrade::StartingBullets) as u16,
+            move_speed: meta_def.base_move_speed
+                * (1f32 + meta_def.move_speed_per_rank * self.rank(MetaUpgrade::MoveSpeed) as f32),
+        }
+    }
+
+    pub fn pickup_chance_multiplier(&self, meta_def: &MetaDef) -> f32 {
+        1f32 + meta_def.pickup_chance_per_rank * self.rank(MetaUpgrade::PickupChance) as f32
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load() -> Self {
+        let Ok(save) = std::fs::read_to_string(SAVE_PATH) else {
+            return Self::default();
+        };
+        ron::from_str(&save).unwrap_or_else(|err| {
+            warn!("could not read {}: {}", SAVE_PATH, err);
+            Self::default()
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|save| std::fs::write(SAVE_PATH, save).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            warn!("could not write {}: {}", SAVE_PATH, err);
+        }
+    }
+
+    // No file system on the web, progress only lasts for the session
+    #[cfg(target_arch = "wasm32")]
+    fn load() -> Self {
+        Self::default()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn save(&self) {}
+}
+
+fn award_coins(
+    meta_def: Res<MetaDef>,
+    mut meta: ResMut<MetaProgress>,
+    mut run_ended: EventReader<RunEnded>,
+) {
+    for ev in run_ended.iter() {
+        let coins = meta_def.coins_for(ev.0.kills, ev.0.seconds);
+        info!("earned {} coins", coins);
+        meta.coins += coins;
+        meta.last_earned = Some(coins);
+        meta.save();
+    }
+}
@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+
+use crate::{
+    ai::AiDeathEvent,
+    bombs::Bombs,
+    difficulty::Difficulty,
+    lives::{lives_left, PendingRespawn},
+    player::{Player, PlayerSlot},
+    powerups::{Buffs, PowerUp, PowerUpDef},
+    run::RunScopedAppExt,
+    waves::{WavePhase, WaveState},
+    RunTime,
+};
+
+pub struct ModePlugin;
+
+impl Plugin for ModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .init_resource::<ModeDef>()
+            .init_run_resource::<RunScore>()
+            .add_systems(Startup, setup_run_hud)
+            .add_systems(Update, display_run_hud);
+    }
+}
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    Endless,
+    Waves,
+    TimeAttack,
+    Survival,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::Endless,
+        GameMode::Waves,
+        GameMode::TimeAttack,
+        GameMode::Survival,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "Endless",
+            GameMode::Waves => "Waves",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Survival => "Survival",
+        }
+    }
+
+    pub fn description(&self, mode_def: &ModeDef) -> String {
+        match self {
+            GameMode::Endless => "Survive as long as you can".to_string(),
+            GameMode::Waves => "Clear every authored wave".to_string(),
+            GameMode::TimeAttack => format!(
+                "Kill as many as you can in {:.0} seconds",
+                mode_def.time_attack_seconds
+            ),
+            GameMode::Survival => format!(
+                "Survive as long as you can with {} lives",
+                mode_def.survival_lives
+            ),
+        }
+    }
+
+    pub fn lives(&self, mode_def: &ModeDef) -> u32 {
+        match self {
+            GameMode::Survival => mode_def.survival_lives,
+            _ => mode_def.lives,
+        }
+    }
+
+    /// Score rules of the mode, from what happened during the run.
+    pub fn score(&self, kills: u32, seconds: f32, waves_cleared: usize) -> u32 {
+        match self {
+            GameMode::Endless | GameMode::Survival => kills * 10 + seconds as u32,
+            GameMode::Waves => kills * 10 + waves_cleared as u32 * 100,
+            GameMode::TimeAttack => kills,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ModeDef {
+    pub time_attack_seconds: f32,
+    pub lives: u32,
+    pub survival_lives: u32,
+}
+
+impl Default for ModeDef {
+    fn default() -> Self {
+        Self {
+            time_attack_seconds: 90f32,
+            lives: 3,
+            survival_lives: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunEnd {
+    Died,
+    TimeUp,
+    Cleared,
+}
+
+impl RunEnd {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunEnd::Died => "died",
+            RunEnd::TimeUp => "time's up",
+            RunEnd::Cleared => "cleared",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RunSummary {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub end: RunEnd,
+    pub score: u32,
+    pub kills: u32,
+    pub seconds: f32,
+    pub seed: u64,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct RunScore {
+    pub kills: u32,
+    /// Kills counted for the score, score multipliers add to it.
+    pub scored_kills: u32,
+    pub score: u32,
+    pub lives_lost: u32,
+    /// Set by the mode when its win or lose condition is met.
+    pub end: Option<RunEnd>,
+}
+
+pub fn uses_spawn_timer(mode: Res<GameMode>) -> bool {
+    *mode != GameMode::Waves
+}
+
+pub fn uses_waves(mode: Res<GameMode>) -> bool {
+    *mode == GameMode::Waves
+}
+
+pub fn score_run(
+    mode: Res<GameMode>,
+    run_time: Res<RunTime>,
+    wave_state: Res<WaveState>,
+    power_up_def: Res<PowerUpDef>,
+    mut score: ResMut<RunScore>,
+    mut ai_killed: EventReader<AiDeathEvent>,
+    q_buffs: Query<&Buffs, With<Player>>,
+) {
+    let kills = ai_killed.iter().count() as u32;
+    let multiplier = if q_buffs
+        .iter()
+        .any(|buffs| buffs.is_active(PowerUp::ScoreMultiplier))
+    {
+        power_up_def.score_multiplier
+    } else {
+        1
+    };
+    score.kills += kills;
+    score.scored_kills += kills * multiplier;
+    score.score = mode.score(
+        score.scored_kills,
+        run_time.0.elapsed_secs(),
+        wave_state.current,
+    );
+}
+
+/// Ends the run as soon as the mode says so, the rest is handled by `player_respawn`.
+pub fn check_run_end(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    mode_def: Res<ModeDef>,
+    run_time: Res<RunTime>,
+    wave_state: Res<WaveState>,
+    mut score: ResMut<RunScore>,
+    q_players: Query<Entity, With<Player>>,
+) {
+    if score.end.is_some() {
+        return;
+    }
+    score.end = match *mode {
+        GameMode::TimeAttack if run_time.0.elapsed_secs() >= mode_def.time_attack_seconds => {
+            Some(RunEnd::TimeUp)
+        }
+        GameMode::Waves if wave_state.phase == WavePhase::Finished => Some(RunEnd::Cleared),
+        _ => None,
+    };
+    if score.end.is_some() {
+        for e in q_players.iter() {
+            commands.entity(e).despawn();
+        }
+    }
+}
+
+#[derive(Component)]
+struct RunHud;
+
+fn setup_run_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(2.),
+            left: Val::Percent(2.),
+            ..default()
+        }),
+        RunHud,
+    ));
+}
+
+fn display_run_hud(
+    mode: Res<GameMode>,
+    mode_def: Res<ModeDef>,
+    run_time: Res<RunTime>,
+    score: Res<RunScore>,
+    pending: Res<PendingRespawn>,
+    q_bombs: Query<(&PlayerSlot, &Bombs)>,
+    mut q_hud: Query<&mut Text, With<RunHud>>,
+) {
+    let Ok(mut text) = q_hud.get_single_mut() else {
+        return;
+    };
+    let mut hud = format!("Score {}  Kills {}", score.score, score.kills);
+    match *mode {
+        GameMode::TimeAttack => {
+            let remaining = (mode_def.time_attack_seconds - run_time.0.elapsed_secs()).max(0f32);
+            hud += &format!("  Time {:.0}", remaining.ceil());
+        }
+        _ => {}
+    }
+    hud += &format!("  Lives {}", lives_left(&mode, &mode_def, &score));
+    let mut respawning = pending.0.iter().collect::<Vec<_>>();
+    respawning.sort_by_key(|(slot, _)| **slot);
+    for (slot, timer) in respawning {
+        hud += &format!(
+            "  P{} respawning in {:.1}",
+            slot + 1,
+            timer.remaining_secs()
+        );
+    }
+    let mut bombs = q_bombs.iter().collect::<Vec<_>>();
+    bombs.sort_by_key(|(slot, _)| slot.0);
+    for (slot, bombs) in bombs {
+        hud += &format!("  P{} bombs {}/{}", slot.0 + 1, bombs.count, bombs.max);
+    }
+    text.sections[0].value = hud;
+}
@@ -0,0 +1,158 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{clock::GameClock, loot::Rarity, utils::move_towards, Pickup, PickupKind};
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PickupDef>();
+    }
+}
+
+#[derive(Resource)]
+pub struct PickupDef {
+    /// Distance at which a body collects a pickup.
+    pub collect_radius: f32,
+    pub magnet_acceleration: f32,
+    pub magnet_max_speed: f32,
+    pub vacuum_seconds: f32,
+    pub health_lifetime: f32,
+    pub weapon_lifetime: f32,
+    pub vacuum_lifetime: f32,
+    pub power_up_lifetime: f32,
+    pub bomb_lifetime: f32,
+    pub xp_lifetime: f32,
+    /// Pickups blink faster and faster during their last seconds.
+    pub expiry_warning_seconds: f32,
+}
+
+impl Default for PickupDef {
+    fn default() -> Self {
+        Self {
+            collect_radius: 20f32,
+            magnet_acceleration: 900f32,
+            magnet_max_speed: 420f32,
+            vacuum_seconds: 1.5f32,
+            health_lifetime: 12f32,
+            weapon_lifetime: 10f32,
+            vacuum_lifetime: 15f32,
+            power_up_lifetime: 10f32,
+            bomb_lifetime: 15f32,
+            xp_lifetime: 20f32,
+            expiry_warning_seconds: 3f32,
+        }
+    }
+}
+
+impl PickupDef {
+    pub fn lifetime(&self, kind: PickupKind, rarity: Rarity) -> f32 {
+        let base = match kind {
+            PickupKind::Health(_) => self.health_lifetime,
+            PickupKind::Weapon(_) => self.weapon_lifetime,
+            PickupKind::Vacuum => self.vacuum_lifetime,
+            PickupKind::PowerUp(_) => self.power_up_lifetime,
+            PickupKind::Bomb => self.bomb_lifetime,
+            PickupKind::Xp(_) => self.xp_lifetime,
+        };
+        let rarity_multiplier = match rarity {
+            Rarity::Common => 1f32,
+            Rarity::Uncommon => 1.25f32,
+            Rarity::Rare => 1.6f32,
+            Rarity::Epic => 2f32,
+        };
+        base * rarity_multiplier
+    }
+}
+
+/// Despawns the pickup when the timer runs out.
+#[derive(Component, Debug)]
+pub struct PickupLifetime(pub Timer);
+
+impl PickupLifetime {
+    pub fn is_visible(&self, warning_seconds: f32) -> bool {
+        let remaining = self.0.remaining_secs();
+        if remaining > warning_seconds {
+            return true;
+        }
+        // Blinks twice a second at first, the rate goes up by 4 every second.
+        let warned = warning_seconds - remaining;
+        (warned * (2f32 + 2f32 * warned)).fract() < 0.5f32
+    }
+}
+
+/// Pickups closer than `radius` fly toward the owner, upgrades raise it.
+#[derive(Component, Debug)]
+pub struct Magnet {
+    pub radius: f32,
+}
+
+/// Given by the vacuum pickup, every pickup is pulled while it lasts.
+#[derive(Component, Debug)]
+pub struct Vacuum(pub Timer);
+
+/// A pickup that got caught by a magnet, it keeps chasing until collected.
+#[derive(Component, Debug)]
+pub struct Attracted {
+    pub speed: f32,
+}
+
+pub fn attract_pickups(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    pickup_def: Res<PickupDef>,
+    mut q_magnets: Query<(Entity, &Transform, &Magnet, Option<&mut Vacuum>), Without<Pickup>>,
+    mut q_pickups: Query<(Entity, &mut Transform, Option<&mut Attracted>), With<Pickup>>,
+) {
+    let magnets = q_magnets
+        .iter_mut()
+        .map(|(e, transform, magnet, vacuum)| {
+            let mut radius = magnet.radius;
+            if let Some(mut vacuum) = vacuum {
+                if vacuum.0.tick(clock.delta()).finished() {
+                    commands.entity(e).remove::<Vacuum>();
+                } else {
+                    radius = f32::INFINITY;
+                }
+            }
+            (transform.translation.xy(), radius)
+        })
+        .collect::<Vec<_>>();
+
+    for (e, mut transform, attracted) in q_pickups.iter_mut() {
+        let position = transform.translation.xy();
+        let Some(&(target, radius)) = magnets.iter().min_by(|a, b| {
+            a.0.distance_squared(position)
+                .total_cmp(&b.0.distance_squared(position))
+        }) else {
+            continue;
+        };
+        let speed = match attracted {
+            Some(mut attracted) => {
+                attracted.speed = (attracted.speed
+                    + pickup_def.magnet_acceleration * clock.delta_seconds())
+                .min(pickup_def.magnet_max_speed);
+                attracted.speed
+            }
+            None if position.distance(target) <= radius => {
+                commands.entity(e).insert(Attracted { speed: 0f32 });
+                continue;
+            }
+            None => continue,
+        };
+        transform.translation = move_towards(position, target, speed * clock.delta_seconds())
+            .extend(transform.translation.z);
+    }
+}
+
+pub fn expire_pickups(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut q_pickups: Query<(Entity, &mut PickupLifetime), Without<Attracted>>,
+) {
+    for (e, mut lifetime) in q_pickups.iter_mut() {
+        if lifetime.0.tick(clock.delta()).finished() {
+            commands.entity(e).despawn();
+        }
+    }
+}
@@ -4,9 +4,10 @@ use bevy_asset_loader::prelude::{AssetCollection, AssetCollectionApp};
 
 use crate::despawn_after::DespawnAfter;
 use crate::menu::GameState;
+use crate::utils::listener_position;
 use crate::{
-    bullets::CommandsSpawnBullet, menu::LastActivity, movement::MoveTarget, Cooldown, TeamIdx,
-    Weapon,
+    bullets::CommandsSpawnBullet, clock::GameClock, controls::PlayerInput, menu::LastActivity,
+    movement::MoveTarget, Cooldown, TeamIdx, Weapon,
 };
 
 pub struct PlayerPlugin;
@@ -14,6 +15,7 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.init_collection::<PlayerSoundAssets>();
+        app.init_resource::<Coop>();
         app.add_event::<PlayerDamagedEvent>();
         app.add_event::<PlayerPickupHealthEvent>();
         app.add_event::<PlayerPickupWeaponEvent>();
@@ -27,6 +29,24 @@ impl Plugin for PlayerPlugin {
 #[derive(Component, Debug)]
 pub struct Player;
 
+/// 0 for the first player, 1 for the second one in co-op.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlayerSlot(pub usize);
+
+/// Local co-op, picked in the menu between runs.
+#[derive(Resource, Debug, Default)]
+pub struct Coop(pub bool);
+
+impl Coop {
+    pub fn players(&self) -> usize {
+        if self.0 {
+            2
+        } else {
+            1
+        }
+    }
+}
+
 #[derive(AssetCollection, Resource)]
 pub struct PlayerSoundAssets {
     #[asset(path = "sounds/player_damage.wav")]
@@ -55,7 +75,7 @@ pub fn handle_player_sounds(
     listener: Query<&Transform, With<Player>>,
 ) {
     for e in damaged_events.iter() {
-        let Ok(listener) = listener.get_single() else{
+        let Some(listener) = listener_position(listener.iter()) else {
             return;
         };
         commands.spawn((
@@ -65,7 +85,7 @@ pub fn handle_player_sounds(
                 spatial: SpatialSettings::new(
                     Transform::IDENTITY,
                     5f32,
-                    ((listener.translation.xy()).normalize_or_zero() * (5f32 / 2f32)).extend(0f32),
+                    (listener.normalize_or_zero() * (5f32 / 2f32)).extend(0f32),
                 ),
             },
             DespawnAfter {
@@ -75,7 +95,7 @@ pub fn handle_player_sounds(
     }
 
     for e in health_events.iter() {
-        let Ok(listener) = listener.get_single() else{
+        let Some(listener) = listener_position(listener.iter()) else {
             return;
         };
         commands.spawn((
@@ -85,7 +105,7 @@ pub fn handle_player_sounds(
                 spatial: SpatialSettings::new(
                     Transform::IDENTITY,
                     5f32,
-                    ((listener.translation.xy()).normalize_or_zero() * (5f32 / 2f32)).extend(0f32),
+                    (listener.normalize_or_zero() * (5f32 / 2f32)).extend(0f32),
                 ),
             },
             DespawnAfter {
@@ -95,7 +115,7 @@ pub fn handle_player_sounds(
     }
 
     for e in weapon_events.iter() {
-        let Ok(listener) = listener.get_single() else{
+        let Some(listener) = listener_position(listener.iter()) else {
             return;
         };
         commands.spawn((
@@ -105,7 +125,7 @@ pub fn handle_player_sounds(
                 spatial: SpatialSettings::new(
                     Transform::IDENTITY,
                     5f32,
-                    ((listener.translation.xy()).normalize_or_zero() * (5f32 / 2f32)).extend(0f32),
+                    (listener.normalize_or_zero() * (5f32 / 2f32)).extend(0f32),
                 ),
             },
             DespawnAfter {
@@ -141,53 +161,43 @@ pub fn handle_mouse_to_move(
 
 pub fn handle_clicks_to_fire(
     mut commands: Commands,
-    time: Res<Time>,
-    q_windows: Query<&Window, With<PrimaryWindow>>,
-    buttons: Res<Input<MouseButton>>,
-    mut q_attackers: Query<
+    clock: Res<GameClock>,
+    q_attackers: Query<
         (
             Entity,
             &Transform,
-            &MoveTarget,
+            &PlayerInput,
             &TeamIdx,
             &Cooldown,
             &Weapon,
         ),
         With<Player>,
     >,
-    camera: Query<(&GlobalTransform, &Camera)>,
     mut last_activity: ResMut<LastActivity>,
 ) {
-    if buttons.pressed(MouseButton::Left) {
-        if let Some(position) = q_windows.single().cursor_position() {
-            if let Some((camera_transform, camera)) = camera.iter().next() {
-                let Some(position) = camera.viewport_to_world_2d(camera_transform, position) else {
-                    return;
-                };
-                for (entity, transform, _, team, cooldown, weapon) in q_attackers.iter_mut() {
-                    let t_position = transform.translation.xy();
-                    // TODO: rework bullet spawn to take place with an event
-                    if commands
-                        .spawn_bullet(
-                            entity,
-                            t_position,
-                            (position - t_position).normalize_or_zero(),
-                            team.clone(),
-                            cooldown,
-                            &time,
-                            weapon.bullets,
-                            weapon.spread,
-                        )
-                        .is_ok()
-                    {
-                        last_activity.0.reset();
-                        commands.entity(entity).insert(Cooldown {
-                            start_time: time.elapsed_seconds(),
-                            duration: cooldown.duration,
-                        });
-                    }
-                }
-            }
+    for (entity, transform, input, team, cooldown, weapon) in q_attackers.iter() {
+        if !input.fire {
+            continue;
+        }
+        // TODO: rework bullet spawn to take place with an event
+        if commands
+            .spawn_bullet(
+                entity,
+                transform.translation.xy(),
+                input.aim,
+                team.clone(),
+                cooldown,
+                &clock,
+                weapon.bullets,
+                weapon.spread,
+            )
+            .is_ok()
+        {
+            last_activity.0.reset();
+            commands.entity(entity).insert(Cooldown {
+                start_time: clock.elapsed_seconds(),
+                duration: cooldown.duration,
+            });
         }
     }
 }
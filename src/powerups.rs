@@ -0,0 +1,241 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use bevy_asset_loader::prelude::{AssetCollection, AssetCollectionApp};
+use serde::Deserialize;
+
+use crate::{
+    affixes::Shield,
+    clock::GameClock,
+    despawn_after::DespawnAfter,
+    menu::GameState,
+    player::{Coop, Player, PlayerSlot},
+    stats::{Modifier, Stat, StatSource, Stats},
+};
+
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_collection::<PowerUpAssets>()
+            .init_resource::<PowerUpDef>()
+            .add_event::<PowerUpPickupEvent>()
+            .add_systems(Startup, setup_buff_hud)
+            .add_systems(Update, display_buff_hud)
+            .add_systems(
+                PostUpdate,
+                handle_power_up_sounds.run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct PowerUpAssets {
+    #[asset(path = "sounds/notification.ogg")]
+    pickup: Handle<AudioSource>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum PowerUp {
+    RapidFire,
+    SpeedBoost,
+    Shield,
+    DoubleDamage,
+    ScoreMultiplier,
+}
+
+impl PowerUp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerUp::RapidFire => "Rapid Fire",
+            PowerUp::SpeedBoost => "Speed Boost",
+            PowerUp::Shield => "Shield",
+            PowerUp::DoubleDamage => "Damage x2",
+            PowerUp::ScoreMultiplier => "Score x2",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PowerUp::RapidFire => Color::ORANGE * 3f32,
+            PowerUp::SpeedBoost => Color::GREEN * 3f32,
+            PowerUp::Shield => Color::CYAN * 4f32,
+            PowerUp::DoubleDamage => Color::RED * 4f32,
+            PowerUp::ScoreMultiplier => Color::GOLD * 3f32,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct PowerUpDef {
+    pub seconds: f32,
+    pub rapid_fire_cooldown_multiplier: f32,
+    pub speed_multiplier: f32,
+    pub shield_hits: u32,
+    pub damage_multiplier: f32,
+    pub score_multiplier: u32,
+}
+
+impl Default for PowerUpDef {
+    fn default() -> Self {
+        Self {
+            seconds: 8f32,
+            rapid_fire_cooldown_multiplier: 0.5f32,
+            speed_multiplier: 1.5f32,
+            shield_hits: 3,
+            damage_multiplier: 2f32,
+            score_multiplier: 2,
+        }
+    }
+}
+
+/// Sent when a player collects a power-up pickup.
+#[derive(Event, Debug)]
+pub struct PowerUpPickupEvent(pub Entity, pub PowerUp);
+
+#[derive(Debug)]
+pub struct Buff {
+    pub power_up: PowerUp,
+    pub timer: Timer,
+}
+
+/// Power-ups active on a player, each one is reverted when its timer runs out.
+#[derive(Component, Debug, Default)]
+pub struct Buffs(pub Vec<Buff>);
+
+impl Buffs {
+    pub fn is_active(&self, power_up: PowerUp) -> bool {
+        self.0.iter().any(|buff| buff.power_up == power_up)
+    }
+}
+
+pub fn apply_power_ups(
+    mut commands: Commands,
+    power_up_def: Res<PowerUpDef>,
+    mut pickup_events: EventReader<PowerUpPickupEvent>,
+    mut q_buffed: Query<(&mut Buffs, &mut Stats)>,
+) {
+    for PowerUpPickupEvent(e, power_up) in pickup_events.iter() {
+        let Ok((mut buffs, mut stats)) = q_buffed.get_mut(*e) else {
+            continue;
+        };
+        // Picking the same power-up again only refreshes it
+        if let Some(buff) = buffs.0.iter_mut().find(|buff| buff.power_up == *power_up) {
+            buff.timer.reset();
+            continue;
+        }
+        let source = StatSource::PowerUp(*power_up);
+        match power_up {
+            PowerUp::RapidFire => stats.add_modifier(
+                source,
+                Stat::FireCooldown,
+                Modifier::Multiply(power_up_def.rapid_fire_cooldown_multiplier),
+            ),
+            PowerUp::SpeedBoost => stats.add_modifier(
+                source,
+                Stat::MoveSpeed,
+                Modifier::Multiply(power_up_def.speed_multiplier),
+            ),
+            PowerUp::Shield => {
+                commands.entity(*e).insert(Shield {
+                    hits: power_up_def.shield_hits,
+                });
+            }
+            PowerUp::DoubleDamage | PowerUp::ScoreMultiplier => {}
+        }
+        buffs.0.push(Buff {
+            power_up: *power_up,
+            timer: Timer::from_seconds(power_up_def.seconds, TimerMode::Once),
+        });
+    }
+}
+
+pub fn expire_buffs(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut q_buffed: Query<(Entity, &mut Buffs, &mut Stats)>,
+) {
+    for (e, mut buffs, mut stats) in q_buffed.iter_mut() {
+        buffs.0.retain_mut(|buff| {
+            if !buff.timer.tick(clock.delta()).finished() {
+                return true;
+            }
+            stats.remove_source(StatSource::PowerUp(buff.power_up));
+            if buff.power_up == PowerUp::Shield {
+                commands.entity(e).remove::<Shield>();
+            }
+            false
+        });
+    }
+}
+
+#[derive(Component)]
+struct BuffHud;
+
+pub fn handle_power_up_sounds(
+    power_up_assets: Res<PowerUpAssets>,
+    mut commands: Commands,
+    mut pickup_events: EventReader<PowerUpPickupEvent>,
+) {
+    for _ in pickup_events.iter() {
+        commands.spawn((
+            AudioBundle {
+                source: power_up_assets.pickup.clone(),
+                settings: PlaybackSettings::ONCE.with_volume(Volume::new_relative(5.0)),
+            },
+            DespawnAfter {
+                timer: Timer::from_seconds(1_f32, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+fn setup_buff_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default()
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(2.),
+                right: Val::Percent(2.),
+                ..default()
+            })
+            .with_text_alignment(TextAlignment::Right),
+        BuffHud,
+    ));
+}
+
+fn display_buff_hud(
+    coop: Res<Coop>,
+    q_buffs: Query<(&PlayerSlot, &Buffs), With<Player>>,
+    mut q_hud: Query<&mut Text, With<BuffHud>>,
+) {
+    let Ok(mut text) = q_hud.get_single_mut() else {
+        return;
+    };
+    let mut buffs = q_buffs.iter().collect::<Vec<_>>();
+    buffs.sort_by_key(|(slot, _)| slot.0);
+    text.sections = buffs
+        .into_iter()
+        .flat_map(|(slot, buffs)| buffs.0.iter().map(move |buff| (slot, buff)))
+        .map(|(slot, buff)| {
+            // Only name the player when there is more than one
+            let who = if coop.0 {
+                format!("P{} ", slot.0 + 1)
+            } else {
+                String::new()
+            };
+            TextSection::new(
+                format!(
+                    "{}{} {:.1}s\n",
+                    who,
+                    buff.power_up.name(),
+                    buff.timer.remaining_secs()
+                ),
+                TextStyle {
+                    font_size: 30.,
+                    color: buff.power_up.color(),
+                    ..default()
+                },
+            )
+        })
+        .collect();
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::run::RunScopedAppExt;
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RunSeed::from_args())
+            .init_run_resource::<RunRng>();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedSource {
+    /// A new seed every run.
+    Random,
+    /// Same seed for everyone on the same day.
+    Daily,
+    Custom(u64),
+}
+
+impl SeedSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SeedSource::Random => "Random",
+            SeedSource::Daily => "Daily",
+            SeedSource::Custom(_) => "Custom",
+        }
+    }
+}
+
+/// Where the seed of the next run comes from, and the seed of the current one.
+#[derive(Resource, Debug)]
+pub struct RunSeed {
+    pub source: SeedSource,
+    pub seed: u64,
+}
+
+impl RunSeed {
+    /// `--seed <number>` or `--daily`, a random seed otherwise.
+    pub fn from_args() -> Self {
+        let mut source = SeedSource::Random;
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--daily" => source = SeedSource::Daily,
+                "--seed" => match args.next().and_then(|seed| seed.parse().ok()) {
+                    Some(seed) => source = SeedSource::Custom(seed),
+                    None => warn!("--seed expects a number"),
+                },
+                _ => {}
+            }
+        }
+        Self { source, seed: 0 }
+    }
+
+    fn roll(&mut self) -> u64 {
+        self.seed = match self.source {
+            SeedSource::Random => rand::thread_rng().gen(),
+            SeedSource::Daily => today(),
+            SeedSource::Custom(seed) => seed,
+        };
+        self.seed
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn today() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs() / 86400)
+        .unwrap_or_default()
+}
+
+// There is no system clock on the web, every day is the first one there.
+#[cfg(target_arch = "wasm32")]
+fn today() -> u64 {
+    0
+}
+
+/// Every random roll of the gameplay goes through this, so a seed gives the same spawn and loot
+/// rolls. Timers tick on the frame delta, so a run is not replayed frame for frame.
+#[derive(Resource)]
+pub struct RunRng(pub StdRng);
+
+impl RunRng {
+    pub fn reseed(&mut self, run_seed: &mut RunSeed) {
+        self.0 = StdRng::seed_from_u64(run_seed.roll());
+        info!("run seed {} ({})", run_seed.seed, run_seed.source.name());
+    }
+}
+
+impl FromWorld for RunRng {
+    fn from_world(world: &mut World) -> Self {
+        let mut run_rng = Self(StdRng::seed_from_u64(0));
+        run_rng.reseed(&mut world.resource_mut::<RunSeed>());
+        run_rng
+    }
+}
@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+
+use crate::modes::RunSummary;
+
+pub struct RunPlugin;
+
+impl Plugin for RunPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RunStarted>()
+            .add_event::<RunEnded>()
+            .init_resource::<LastRun>()
+            .add_systems(Last, record_last_run);
+    }
+}
+
+/// Sent once the arena is reset for a new run, run scoped resources are rebuilt from it.
+#[derive(Event, Debug, Default)]
+pub struct RunStarted;
+
+#[derive(Event, Debug)]
+pub struct RunEnded(pub RunSummary);
+
+#[derive(Resource, Debug, Default)]
+pub struct LastRun(pub Option<RunSummary>);
+
+pub trait RunScopedAppExt {
+    /// Like `init_resource`, but the resource is built again every time a run starts.
+    fn init_run_resource<R: Resource + FromWorld>(&mut self) -> &mut Self;
+}
+
+impl RunScopedAppExt for App {
+    fn init_run_resource<R: Resource + FromWorld>(&mut self) -> &mut Self {
+        self.init_resource::<R>().add_systems(
+            Last,
+            reset_run_resource::<R>.run_if(on_event::<RunStarted>()),
+        )
+    }
+}
+
+fn reset_run_resource<R: Resource + FromWorld>(world: &mut World) {
+    let resource = R::from_world(world);
+    world.insert_resource(resource);
+}
+
+fn record_last_run(mut run_ended: EventReader<RunEnded>, mut last_run: ResMut<LastRun>) {
+    for ev in run_ended.iter() {
+        info!("run over: {:?}", ev.0);
+        last_run.0 = Some(ev.0);
+    }
+}
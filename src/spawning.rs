@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use crate::{
+    affixes::{Affix, AffixDef},
+    ai::{spawn_ai, Ai, AiKind},
+    clock::GameClock,
+    difficulty::DifficultyLevel,
+    player::Player,
+    rng::RunRng,
+    run::RunScopedAppExt,
+    RemoveOnRespawn,
+};
+
+pub struct SpawningPlugin;
+
+impl Plugin for SpawningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpawnDef>()
+            .init_run_resource::<SpawnQueue>()
+            .init_run_resource::<ActiveSpawnMode>();
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SpawnMode {
+    /// Anywhere in the arena.
+    #[default]
+    Arena,
+    /// On the border of the arena, enemies walk in.
+    Edge,
+    /// Around a few fixed portals.
+    Portal,
+}
+
+#[derive(Resource)]
+pub struct SpawnDef {
+    /// Used until a wave asks for another one.
+    pub mode: SpawnMode,
+    pub half_extents: Vec2,
+    pub min_player_distance: f32,
+    pub telegraph_seconds: f32,
+    pub portals: Vec<Vec2>,
+    pub portal_scatter: f32,
+    /// AIs and telegraphs alive at once, extra spawns wait in the `SpawnQueue`.
+    pub max_alive_ais: usize,
+    /// Spawns requested while the queue is full are dropped.
+    pub max_queued_spawns: usize,
+}
+
+impl Default for SpawnDef {
+    fn default() -> Self {
+        Self {
+            mode: SpawnMode::Arena,
+            half_extents: Vec2::new(460f32, 260f32),
+            min_player_distance: 160f32,
+            telegraph_seconds: 1f32,
+            portals: vec![
+                Vec2::new(-380f32, 200f32),
+                Vec2::new(380f32, 200f32),
+                Vec2::new(-380f32, -200f32),
+                Vec2::new(380f32, -200f32),
+            ],
+            portal_scatter: 30f32,
+            max_alive_ais: 60,
+            max_queued_spawns: 40,
+        }
+    }
+}
+
+/// Where spawns appear during the current run, each wave sets its own.
+#[derive(Resource, Debug)]
+pub struct ActiveSpawnMode(pub SpawnMode);
+
+impl FromWorld for ActiveSpawnMode {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<SpawnDef>().mode)
+    }
+}
+
+impl SpawnDef {
+    fn candidate(&self, mode: SpawnMode, rng: &mut impl Rng) -> Vec2 {
+        let extents = self.half_extents;
+        match mode {
+            SpawnMode::Arena => Vec2::new(
+                rng.gen_range(-extents.x..extents.x),
+                rng.gen_range(-extents.y..extents.y),
+            ),
+            SpawnMode::Edge => {
+                // Pick a point along the perimeter so long sides get more spawns
+                let mut d = rng.gen_range(0f32..(extents.x + extents.y) * 4f32);
+                if d < extents.x * 2f32 {
+                    return Vec2::new(d - extents.x, extents.y);
+                }
+                d -= extents.x * 2f32;
+                if d < extents.x * 2f32 {
+                    return Vec2::new(d - extents.x, -extents.y);
+                }
+                d -= extents.x * 2f32;
+                if d < extents.y * 2f32 {
+                    return Vec2::new(-extents.x, d - extents.y);
+                }
+                d -= extents.y * 2f32;
+                Vec2::new(extents.x, d - extents.y)
+            }
+            SpawnMode::Portal => {
+                let portal = self
+                    .portals
+                    .choose(&mut *rng)
+                    .copied()
+                    .unwrap_or(Vec2::ZERO);
+                let angle = rng.gen_range(0f32..std::f32::consts::TAU);
+                portal + Vec2::from_angle(angle) * rng.gen_range(0f32..self.portal_scatter)
+            }
+        }
+    }
+
+    /// A spawn point at least `min_player_distance` from every player,
+    /// or the furthest one found if the arena is too crowded for that.
+    pub fn pick_position(&self, mode: SpawnMode, players: &[Vec2], rng: &mut impl Rng) -> Vec2 {
+        let distance_to_players = |position: Vec2| {
+            players
+                .iter()
+                .map(|player| player.distance(position))
+                .fold(f32::MAX, f32::min)
+        };
+        let mut best = self.candidate(mode, &mut *rng);
+        let mut best_distance = distance_to_players(best);
+        for _ in 0..16 {
+            if best_distance >= self.min_player_distance {
+                break;
+            }
+            let candidate = self.candidate(mode, &mut *rng);
+            let distance = distance_to_players(candidate);
+            if distance > best_distance {
+                best = candidate;
+                best_distance = distance;
+            }
+        }
+        best
+    }
+}
+
+pub struct QueuedSpawn {
+    pub kind: AiKind,
+    /// Picked by `SpawnDef` when the spawn leaves the queue if not set.
+    pub position: Option<Vec2>,
+    pub affixes: Vec<Affix>,
+    pub telegraph: bool,
+}
+
+/// Every spawn goes through here so the number of alive AIs stays bounded.
+#[derive(Resource, Default)]
+pub struct SpawnQueue(pub VecDeque<QueuedSpawn>);
+
+impl SpawnQueue {
+    pub fn push(&mut self, spawn_def: &SpawnDef, spawn: QueuedSpawn) {
+        if self.0.len() >= spawn_def.max_queued_spawns {
+            warn!("spawn queue is full, dropping a {:?} spawn", spawn.kind);
+            return;
+        }
+        self.0.push_back(spawn);
+    }
+}
+
+/// Marker shown where an enemy is about to appear.
+#[derive(Component)]
+pub struct SpawnTelegraph {
+    pub kind: AiKind,
+    pub affixes: Vec<Affix>,
+    pub timer: Timer,
+}
+
+pub fn drain_spawn_queue(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    spawn_def: Res<SpawnDef>,
+    affix_def: Res<AffixDef>,
+    level: Res<DifficultyLevel>,
+    spawn_mode: Res<ActiveSpawnMode>,
+    mut queue: ResMut<SpawnQueue>,
+    mut run_rng: ResMut<RunRng>,
+    q_players: Query<&Transform, With<Player>>,
+    q_alive: Query<(), Or<(With<Ai>, With<SpawnTelegraph>)>>,
+) {
+    let mut alive = q_alive.iter().count();
+    let players = q_players
+        .iter()
+        .map(|transform| transform.translation.xy())
+        .collect::<Vec<_>>();
+    let rng = &mut run_rng.0;
+    while alive < spawn_def.max_alive_ais {
+        let Some(spawn) = queue.0.pop_front() else {
+            break;
+        };
+        let position = spawn
+            .position
+            .unwrap_or_else(|| spawn_def.pick_position(spawn_mode.0, &players, rng));
+        if spawn.telegraph {
+            spawn_telegraph(
+                &mut commands,
+                &spawn_def,
+                spawn.kind,
+                position,
+                spawn.affixes,
+            );
+        } else {
+            spawn_ai(
+                &mut commands,
+                spawn.kind,
+                position,
+                spawn.affixes,
+                &affix_def,
+                level.health_multiplier,
+                clock.elapsed_seconds(),
+                rng,
+            );
+        }
+        alive += 1;
+    }
+}
+
+pub fn spawn_telegraph(
+    commands: &mut Commands,
+    spawn_def: &SpawnDef,
+    kind: AiKind,
+    position: Vec2,
+    affixes: Vec<Affix>,
+) {
+    commands.spawn((
+        Transform::from_translation(position.extend(2f32)),
+        SpawnTelegraph {
+            kind,
+            affixes,
+            timer: Timer::from_seconds(spawn_def.telegraph_seconds, TimerMode::Once),
+        },
+        RemoveOnRespawn,
+    ));
+}
+
+pub fn hatch_telegraphs(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    affix_def: Res<AffixDef>,
+    level: Res<DifficultyLevel>,
+    mut run_rng: ResMut<RunRng>,
+    mut q_telegraphs: Query<(Entity, &Transform, &mut SpawnTelegraph)>,
+) {
+    let rng = &mut run_rng.0;
+    for (e, transform, mut telegraph) in q_telegraphs.iter_mut() {
+        telegraph.timer.tick(clock.delta());
+        if !telegraph.timer.finished() {
+            continue;
+        }
+        commands.entity(e).despawn();
+        spawn_ai(
+            &mut commands,
+            telegraph.kind,
+            transform.translation.xy(),
+            std::mem::take(&mut telegraph.affixes),
+            &affix_def,
+            level.health_multiplier,
+            clock.elapsed_seconds(),
+            rng,
+        );
+    }
+}
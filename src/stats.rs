@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    affixes::Affix, dash::Dash, movement::MoveSpeed, pickups::Magnet, powerups::PowerUp,
+    upgrades::UpgradeKind, Cooldown, Health, Weapon,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stat {
+    MaxHealth,
+    Bullets,
+    FireCooldown,
+    MoveSpeed,
+    MagnetRadius,
+    DashDistance,
+    DashCooldown,
+}
+
+/// Where a modifier comes from, all the modifiers of a source are removed together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatSource {
+    Pickup,
+    Upgrade(UpgradeKind),
+    PowerUp(PowerUp),
+    Affix(Affix),
+    Difficulty,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Modifier {
+    Add(f32),
+    Multiply(f32),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StatModifier {
+    pub source: StatSource,
+    pub stat: Stat,
+    pub modifier: Modifier,
+}
+
+/// Base values and modifiers, `apply_stats` writes the final values into the
+/// `Health`, `Weapon`, `Cooldown`, `MoveSpeed`, `Magnet` and `Dash` components.
+#[derive(Component, Debug, Default)]
+pub struct Stats {
+    base: HashMap<Stat, f32>,
+    modifiers: Vec<StatModifier>,
+}
+
+impl Stats {
+    pub fn with_base(mut self, stat: Stat, value: f32) -> Self {
+        self.set_base(stat, value);
+        self
+    }
+
+    pub fn with_modifier(mut self, source: StatSource, stat: Stat, modifier: Modifier) -> Self {
+        self.add_modifier(source, stat, modifier);
+        self
+    }
+
+    pub fn set_base(&mut self, stat: Stat, value: f32) {
+        self.base.insert(stat, value);
+    }
+
+    /// Modifiers stack, adding the same one twice counts twice.
+    pub fn add_modifier(&mut self, source: StatSource, stat: Stat, modifier: Modifier) {
+        self.modifiers.push(StatModifier {
+            source,
+            stat,
+            modifier,
+        });
+    }
+
+    /// Grows the one additive modifier of a source instead of stacking another,
+    /// the stat with every other modifier applied stays at or under `max`.
+    pub fn grow_modifier(&mut self, source: StatSource, stat: Stat, amount: f32, max: f32) {
+        let existing = self.modifiers.iter().position(|modifier| {
+            modifier.source == source
+                && modifier.stat == stat
+                && matches!(modifier.modifier, Modifier::Add(_))
+        });
+        let current = match existing.map(|i| self.modifiers[i].modifier) {
+            Some(Modifier::Add(value)) => value,
+            _ => 0f32,
+        };
+        let others = self.get(stat).unwrap_or_default() - current;
+        // Never takes back what was already picked up
+        let value = (current + amount).min(max - others).max(current);
+        match existing {
+            Some(i) => self.modifiers[i].modifier = Modifier::Add(value),
+            None => self.add_modifier(source, stat, Modifier::Add(value)),
+        }
+    }
+
+    pub fn remove_source(&mut self, source: StatSource) {
+        self.modifiers.retain(|modifier| modifier.source != source);
+    }
+
+    /// Additive modifiers are summed with the base before the multipliers apply.
+    pub fn get(&self, stat: Stat) -> Option<f32> {
+        let base = *self.base.get(&stat)?;
+        let (add, multiply) = self
+            .modifiers
+            .iter()
+            .filter(|modifier| modifier.stat == stat)
+            .fold((0f32, 1f32), |(add, multiply), modifier| {
+                match modifier.modifier {
+                    Modifier::Add(value) => (add + value, multiply),
+                    Modifier::Multiply(value) => (add, multiply * value),
+                }
+            });
+        Some((base + add) * multiply)
+    }
+
+    /// Bullets per shot, at least one and no more than the weapon can fire.
+    pub fn bullets(&self, max: u16) -> Option<u16> {
+        self.get(Stat::Bullets)
+            .map(|bullets| (bullets.round().max(1f32) as u16).min(max))
+    }
+
+    /// The components below are what `apply_stats` keeps up to date, spawners build them here.
+    pub fn health(&self) -> Health {
+        let max = self.get(Stat::MaxHealth).unwrap_or(1f32);
+        Health { current: max, max }
+    }
+
+    pub fn move_speed(&self) -> MoveSpeed {
+        MoveSpeed(self.get(Stat::MoveSpeed).unwrap_or_default())
+    }
+
+    pub fn weapon(&self, max: u16, spread: f32) -> Weapon {
+        Weapon {
+            bullets: self.bullets(max).unwrap_or(1),
+            max,
+            spread,
+        }
+    }
+
+    pub fn cooldown(&self) -> Cooldown {
+        Cooldown {
+            start_time: 0f32,
+            duration: self.get(Stat::FireCooldown).unwrap_or_default(),
+        }
+    }
+}
+
+/// Runs before anything fires, `Cooldown` is copied when a shot is taken.
+pub fn apply_stats(
+    mut q_stats: Query<
+        (
+            &Stats,
+            Option<&mut Health>,
+            Option<&mut Weapon>,
+            Option<&mut Cooldown>,
+            Option<&mut MoveSpeed>,
+            Option<&mut Magnet>,
+            Option<&mut Dash>,
+        ),
+        Changed<Stats>,
+    >,
+) {
+    for (stats, health, weapon, cooldown, speed, magnet, dash) in q_stats.iter_mut() {
+        if let (Some(mut health), Some(max)) = (health, stats.get(Stat::MaxHealth)) {
+            // Raising the max heals by as much, lowering it only caps the current health
+            let gained = (max - health.max).max(0f32);
+            health.max = max;
+            health.current = (health.current + gained).min(max);
+        }
+        if let Some(mut weapon) = weapon {
+            if let Some(bullets) = stats.bullets(weapon.max) {
+                weapon.bullets = bullets;
+            }
+        }
+        if let (Some(mut cooldown), Some(duration)) = (cooldown, stats.get(Stat::FireCooldown)) {
+            cooldown.duration = duration;
+        }
+        if let (Some(mut speed), Some(value)) = (speed, stats.get(Stat::MoveSpeed)) {
+            speed.0 = value;
+        }
+        if let (Some(mut magnet), Some(radius)) = (magnet, stats.get(Stat::MagnetRadius)) {
+            magnet.radius = radius;
+        }
+        if let Some(mut dash) = dash {
+            if let Some(distance) = stats.get(Stat::DashDistance) {
+                dash.distance = distance;
+            }
+            if let Some(cooldown) = stats.get(Stat::DashCooldown) {
+                dash.cooldown = cooldown;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_before_multiplying() {
+        let stats = Stats::default()
+            .with_base(Stat::MoveSpeed, 100f32)
+            .with_modifier(
+                StatSource::Difficulty,
+                Stat::MoveSpeed,
+                Modifier::Multiply(2f32),
+            )
+            .with_modifier(StatSource::Pickup, Stat::MoveSpeed, Modifier::Add(50f32));
+        assert_eq!(stats.get(Stat::MoveSpeed), Some(300f32));
+        assert_eq!(stats.get(Stat::MaxHealth), None);
+    }
+
+    #[test]
+    fn removes_every_modifier_of_a_source() {
+        let mut stats = Stats::default()
+            .with_base(Stat::MaxHealth, 10f32)
+            .with_modifier(StatSource::Pickup, Stat::MaxHealth, Modifier::Add(5f32))
+            .with_modifier(
+                StatSource::Pickup,
+                Stat::MaxHealth,
+                Modifier::Multiply(2f32),
+            )
+            .with_modifier(StatSource::Difficulty, Stat::MaxHealth, Modifier::Add(1f32));
+        stats.remove_source(StatSource::Pickup);
+        assert_eq!(stats.get(Stat::MaxHealth), Some(11f32));
+    }
+
+    #[test]
+    fn grows_one_modifier_up_to_the_cap() {
+        let mut stats = Stats::default()
+            .with_base(Stat::Bullets, 1f32)
+            .with_modifier(
+                StatSource::Upgrade(UpgradeKind::MoreBullets),
+                Stat::Bullets,
+                Modifier::Add(2f32),
+            );
+        stats.grow_modifier(StatSource::Pickup, Stat::Bullets, 1f32, 5f32);
+        assert_eq!(stats.get(Stat::Bullets), Some(4f32));
+        stats.grow_modifier(StatSource::Pickup, Stat::Bullets, 3f32, 5f32);
+        assert_eq!(stats.get(Stat::Bullets), Some(5f32));
+        assert_eq!(stats.modifiers.len(), 2);
+        // The upgrade is still there once the pickups are gone
+        stats.remove_source(StatSource::Pickup);
+        assert_eq!(stats.get(Stat::Bullets), Some(3f32));
+    }
+}
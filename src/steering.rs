@@ -0,0 +1,94 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    ai::Ai,
+    clock::GameClock,
+    movement::{MoveSpeed, MoveTarget},
+};
+
+#[derive(Resource)]
+pub struct SteeringDef {
+    pub neighbour_radius: f32,
+    pub separation_radius: f32,
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+}
+
+impl Default for SteeringDef {
+    fn default() -> Self {
+        Self {
+            neighbour_radius: 90f32,
+            separation_radius: 40f32,
+            separation_weight: 1.6f32,
+            alignment_weight: 0.3f32,
+            cohesion_weight: 0.15f32,
+        }
+    }
+}
+
+/// Nudges AIs on top of `move_targets` so they don't collapse into one blob
+/// when they all chase points around the same player.
+pub fn ai_steering(
+    clock: Res<GameClock>,
+    steering: Res<SteeringDef>,
+    mut q_ais: Query<(Entity, &mut Transform, &MoveTarget, &MoveSpeed), With<Ai>>,
+) {
+    let boids = q_ais
+        .iter()
+        .map(|(e, transform, target, _)| {
+            let position = transform.translation.xy();
+            let heading = target
+                .target
+                .map(|t| (t - position).normalize_or_zero())
+                .unwrap_or(Vec2::ZERO);
+            (e, position, heading)
+        })
+        .collect::<Vec<_>>();
+
+    for (e, mut transform, target, speed) in q_ais.iter_mut() {
+        let position = transform.translation.xy();
+        let heading = target
+            .target
+            .map(|t| (t - position).normalize_or_zero())
+            .unwrap_or(Vec2::ZERO);
+
+        let mut separation = Vec2::ZERO;
+        let mut headings = Vec2::ZERO;
+        let mut centre = Vec2::ZERO;
+        let mut neighbours = 0;
+        for (other, other_position, other_heading) in boids.iter() {
+            if *other == e {
+                continue;
+            }
+            let distance = position.distance(*other_position);
+            if distance > steering.neighbour_radius {
+                continue;
+            }
+            if distance < steering.separation_radius {
+                // Enemies spawned on the same spot have no direction to push along,
+                // so derive a stable one from the entity to split them apart.
+                let away = if distance > 0.01f32 {
+                    (position - *other_position) / distance
+                } else {
+                    Vec2::from_angle(e.index() as f32 * 2.399963f32)
+                };
+                separation += away * (1f32 - distance / steering.separation_radius);
+            }
+            headings += *other_heading;
+            centre += *other_position;
+            neighbours += 1;
+        }
+        if neighbours == 0 {
+            continue;
+        }
+        let alignment = headings / neighbours as f32 - heading;
+        let cohesion = (centre / neighbours as f32 - position) / steering.neighbour_radius;
+
+        let steer = (separation * steering.separation_weight
+            + alignment * steering.alignment_weight
+            + cohesion * steering.cohesion_weight)
+            .clamp_length_max(1f32);
+        transform.translation += (steer * speed.0 * clock.delta_seconds()).extend(0f32);
+    }
+}
@@ -0,0 +1,254 @@
+use bevy::{ecs::query::WorldQuery, prelude::*};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    bombs::Bombs,
+    loot::Rarity,
+    stats::{Modifier, Stat, StatSource, Stats},
+};
+
+pub struct UpgradePlugin;
+
+impl Plugin for UpgradePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UpgradePool>();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradeKind {
+    MoreBullets,
+    FasterCooldown,
+    MoveSpeed,
+    MaxHealth,
+    MagnetRadius,
+    BombCapacity,
+    Pierce,
+    Overcharge,
+    DashDistance,
+    DashCooldown,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpgradeDef {
+    pub kind: UpgradeKind,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub rarity: Rarity,
+    /// How many times it can be picked during a run.
+    pub max_stacks: u32,
+    /// Upgrades that have to be owned first.
+    pub requires: Vec<UpgradeKind>,
+}
+
+/// Everything a level-up can offer.
+#[derive(Resource)]
+pub struct UpgradePool {
+    pub upgrades: Vec<UpgradeDef>,
+    pub choices: usize,
+}
+
+impl Default for UpgradePool {
+    fn default() -> Self {
+        let upgrade =
+            |kind, name, description, rarity, max_stacks, requires: &[UpgradeKind]| UpgradeDef {
+                kind,
+                name,
+                description,
+                rarity,
+                max_stacks,
+                requires: requires.to_vec(),
+            };
+        Self {
+            upgrades: vec![
+                upgrade(
+                    UpgradeKind::MoreBullets,
+                    "Volley",
+                    "+1 bullet per shot",
+                    Rarity::Common,
+                    10,
+                    &[],
+                ),
+                upgrade(
+                    UpgradeKind::FasterCooldown,
+                    "Trigger Happy",
+                    "-15% time between shots",
+                    Rarity::Common,
+                    6,
+                    &[],
+                ),
+                upgrade(
+                    UpgradeKind::MoveSpeed,
+                    "Light Feet",
+                    "+10% move speed",
+                    Rarity::Common,
+                    5,
+                    &[],
+                ),
+                upgrade(
+                    UpgradeKind::MaxHealth,
+                    "Thick Skin",
+                    "+0.5 max health",
+                    Rarity::Common,
+                    5,
+                    &[],
+                ),
+                upgrade(
+                    UpgradeKind::MagnetRadius,
+                    "Magnetism",
+                    "+40 pickup magnet radius",
+                    Rarity::Uncommon,
+                    4,
+                    &[],
+                ),
+                upgrade(
+                    UpgradeKind::BombCapacity,
+                    "Bandolier",
+                    "+1 bomb capacity and a bomb",
+                    Rarity::Uncommon,
+                    3,
+                    &[],
+                ),
+                upgrade(
+                    UpgradeKind::Pierce,
+                    "Piercing Rounds",
+                    "Bullets go through one more enemy",
+                    Rarity::Rare,
+                    3,
+                    &[UpgradeKind::MoreBullets],
+                ),
+                upgrade(
+                    UpgradeKind::Overcharge,
+                    "Overcharge",
+                    "+3 bullets per shot and -25% time between shots",
+                    Rarity::Epic,
+                    1,
+                    &[UpgradeKind::MoreBullets, UpgradeKind::FasterCooldown],
+                ),
+                upgrade(
+                    UpgradeKind::DashDistance,
+                    "Long Stride",
+                    "+25% dash distance",
+                    Rarity::Uncommon,
+                    4,
+                    &[],
+                ),
+                upgrade(
+                    UpgradeKind::DashCooldown,
+                    "Second Wind",
+                    "-15% time between dashes",
+                    Rarity::Uncommon,
+                    4,
+                    &[],
+                ),
+            ],
+            choices: 3,
+        }
+    }
+}
+
+impl UpgradePool {
+    pub fn get(&self, kind: UpgradeKind) -> &UpgradeDef {
+        self.upgrades
+            .iter()
+            .find(|upgrade| upgrade.kind == kind)
+            .expect("every upgrade kind is in the pool")
+    }
+
+    /// Distinct upgrades the owner can still take, weighted by rarity.
+    pub fn roll_choices(&self, owned: &Upgrades, rng: &mut impl Rng) -> Vec<UpgradeKind> {
+        let mut available = self
+            .upgrades
+            .iter()
+            .filter(|upgrade| owned.stacks(upgrade.kind) < upgrade.max_stacks)
+            .filter(|upgrade| upgrade.requires.iter().all(|kind| owned.stacks(*kind) > 0))
+            .collect::<Vec<_>>();
+        let mut choices = Vec::new();
+        while choices.len() < self.choices {
+            let Ok(picked) = available
+                .choose_weighted(&mut *rng, |upgrade| rarity_weight(upgrade.rarity))
+                .map(|upgrade| upgrade.kind)
+            else {
+                break;
+            };
+            available.retain(|upgrade| upgrade.kind != picked);
+            choices.push(picked);
+        }
+        choices
+    }
+}
+
+fn rarity_weight(rarity: Rarity) -> f32 {
+    match rarity {
+        Rarity::Common => 10f32,
+        Rarity::Uncommon => 5f32,
+        Rarity::Rare => 2f32,
+        Rarity::Epic => 1f32,
+    }
+}
+
+/// Upgrades picked so far during the run.
+#[derive(Component, Debug, Default)]
+pub struct Upgrades(pub Vec<UpgradeKind>);
+
+impl Upgrades {
+    pub fn stacks(&self, kind: UpgradeKind) -> u32 {
+        self.0.iter().filter(|owned| **owned == kind).count() as u32
+    }
+}
+
+/// Bullets of the owner go through that many enemies before despawning.
+#[derive(Component, Debug)]
+pub struct Pierce(pub u32);
+
+/// What an upgrade can change on its owner.
+#[derive(WorldQuery)]
+#[world_query(mutable)]
+pub struct UpgradeTarget {
+    pub entity: Entity,
+    pub upgrades: &'static mut Upgrades,
+    pub stats: &'static mut Stats,
+    pub bombs: &'static mut Bombs,
+    pub pierce: Option<&'static mut Pierce>,
+}
+
+pub fn apply_upgrade(commands: &mut Commands, target: &mut UpgradeTargetItem, kind: UpgradeKind) {
+    target.upgrades.0.push(kind);
+    let source = StatSource::Upgrade(kind);
+    let stats = &mut target.stats;
+    match kind {
+        UpgradeKind::MoreBullets => stats.add_modifier(source, Stat::Bullets, Modifier::Add(1f32)),
+        UpgradeKind::FasterCooldown => {
+            stats.add_modifier(source, Stat::FireCooldown, Modifier::Multiply(0.85f32));
+        }
+        UpgradeKind::MoveSpeed => {
+            stats.add_modifier(source, Stat::MoveSpeed, Modifier::Multiply(1.1f32));
+        }
+        UpgradeKind::MaxHealth => {
+            stats.add_modifier(source, Stat::MaxHealth, Modifier::Add(0.5f32))
+        }
+        UpgradeKind::MagnetRadius => {
+            stats.add_modifier(source, Stat::MagnetRadius, Modifier::Add(40f32));
+        }
+        UpgradeKind::BombCapacity => {
+            target.bombs.max += 1;
+            target.bombs.count += 1;
+        }
+        UpgradeKind::Pierce => match target.pierce.as_mut() {
+            Some(pierce) => pierce.0 += 1,
+            None => {
+                commands.entity(target.entity).insert(Pierce(1));
+            }
+        },
+        UpgradeKind::Overcharge => {
+            stats.add_modifier(source, Stat::Bullets, Modifier::Add(3f32));
+            stats.add_modifier(source, Stat::FireCooldown, Modifier::Multiply(0.75f32));
+        }
+        UpgradeKind::DashDistance => {
+            stats.add_modifier(source, Stat::DashDistance, Modifier::Multiply(1.25f32));
+        }
+        UpgradeKind::DashCooldown => {
+            stats.add_modifier(source, Stat::DashCooldown, Modifier::Multiply(0.85f32));
+        }
+    }
+}
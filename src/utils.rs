@@ -8,3 +8,18 @@ pub fn move_towards(from: Vec2, to: Vec2, max_distance: f32) -> Vec2 {
     }
     return from + (to_target / total_distance) * max_distance;
 }
+
+/// Sounds are heard from between the players.
+pub fn listener_position<'a>(players: impl Iterator<Item = &'a Transform>) -> Option<Vec2> {
+    let (sum, count) = players.fold((Vec2::ZERO, 0), |(sum, count), transform| {
+        (sum + transform.translation.truncate(), count + 1)
+    });
+    (count > 0).then(|| sum / count as f32)
+}
+
+pub fn nearest(positions: &[Vec2], from: Vec2) -> Option<Vec2> {
+    positions.iter().copied().min_by(|a, b| {
+        a.distance_squared(from)
+            .total_cmp(&b.distance_squared(from))
+    })
+}
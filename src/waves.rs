@@ -0,0 +1,336 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    math::Vec3Swizzles,
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+use bevy_asset_loader::prelude::{AssetCollection, AssetCollectionApp};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    affixes::AffixDef,
+    ai::{Ai, AiDeathEvent, AiKind},
+    clock::GameClock,
+    modes::GameMode,
+    player::Player,
+    rng::RunRng,
+    run::RunScopedAppExt,
+    spawning::{ActiveSpawnMode, QueuedSpawn, SpawnDef, SpawnMode, SpawnQueue, SpawnTelegraph},
+    RunTime,
+};
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<WaveSet>()
+            .init_asset_loader::<WaveSetLoader>()
+            .init_collection::<WaveAssets>()
+            .init_run_resource::<WaveState>()
+            .add_systems(Startup, setup_wave_banner)
+            .add_systems(Update, display_wave_banner);
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct WaveAssets {
+    #[asset(path = "waves/default.waves.ron")]
+    pub waves: Handle<WaveSet>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Formation {
+    /// Every member gets its own spawn point.
+    Scattered,
+    /// Members bunch up around one spawn point.
+    Cluster { radius: f32 },
+    /// Members stand in a line facing the player.
+    Line { spacing: f32 },
+    /// Members surround the player.
+    Ring { radius: f32 },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum ClearCondition {
+    AllDead,
+    Survive(f32),
+    Kills(u32),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaveGroup {
+    pub kind: AiKind,
+    pub count: u32,
+    /// Seconds after the wave starts before the first member appears.
+    pub delay: f32,
+    /// Seconds between two members, 0 spawns the whole group at once.
+    pub interval: f32,
+    pub formation: Formation,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaveDef {
+    pub name: String,
+    pub groups: Vec<WaveGroup>,
+    pub clear: ClearCondition,
+    /// Where the members of the wave appear.
+    #[serde(default)]
+    pub spawn: SpawnMode,
+}
+
+#[derive(Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "8b7c2c47-3c3e-4a57-9f3a-52c1f0a4e0d1"]
+pub struct WaveSet {
+    pub breather_seconds: f32,
+    pub waves: Vec<WaveDef>,
+}
+
+#[derive(Default)]
+pub struct WaveSetLoader;
+
+impl AssetLoader for WaveSetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let wave_set: WaveSet = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(wave_set));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum WavePhase {
+    #[default]
+    Breather,
+    Running,
+    Finished,
+}
+
+struct PendingSpawn {
+    at: f32,
+    group: usize,
+    member: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct WaveState {
+    /// Index of the wave that is running, or coming up during a breather.
+    pub current: usize,
+    pub phase: WavePhase,
+    pub phase_seconds: f32,
+    pub kills: u32,
+    pending: Vec<PendingSpawn>,
+    anchors: Vec<Option<Vec2>>,
+}
+
+#[derive(Component)]
+struct WaveBanner;
+
+fn setup_wave_banner(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 50.,
+                color: Color::ORANGE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(15.),
+            width: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            display: Display::None,
+            ..default()
+        })
+        .with_text_alignment(TextAlignment::Center),
+        WaveBanner,
+    ));
+}
+
+fn formation_position(
+    formation: Formation,
+    anchor: Vec2,
+    player: Vec2,
+    member: u32,
+    count: u32,
+    rng: &mut impl Rng,
+) -> Vec2 {
+    match formation {
+        Formation::Scattered => anchor,
+        Formation::Cluster { radius } => {
+            let angle = rng.gen_range(0f32..std::f32::consts::TAU);
+            anchor + Vec2::from_angle(angle) * rng.gen_range(0f32..radius)
+        }
+        Formation::Line { spacing } => {
+            let across = (player - anchor).normalize_or_zero().perp();
+            let offset = member as f32 - (count - 1) as f32 / 2f32;
+            anchor + across * offset * spacing
+        }
+        Formation::Ring { radius } => {
+            let angle = std::f32::consts::TAU / count as f32 * member as f32;
+            player + Vec2::from_angle(angle) * radius
+        }
+    }
+}
+
+pub fn run_waves(
+    clock: Res<GameClock>,
+    wave_assets: Res<WaveAssets>,
+    wave_sets: Res<Assets<WaveSet>>,
+    spawn_def: Res<SpawnDef>,
+    affix_def: Res<AffixDef>,
+    run_time: Res<RunTime>,
+    mut state: ResMut<WaveState>,
+    mut spawn_queue: ResMut<SpawnQueue>,
+    mut spawn_mode: ResMut<ActiveSpawnMode>,
+    mut run_rng: ResMut<RunRng>,
+    mut ai_killed: EventReader<AiDeathEvent>,
+    q_players: Query<&Transform, With<Player>>,
+    q_alive: Query<(), Or<(With<Ai>, With<SpawnTelegraph>)>>,
+) {
+    let kills = ai_killed.iter().count() as u32;
+    let Some(wave_set) = wave_sets.get(&wave_assets.waves) else {
+        return;
+    };
+    state.phase_seconds += clock.delta_seconds();
+    match state.phase {
+        WavePhase::Finished => {}
+        WavePhase::Breather => {
+            if state.phase_seconds < wave_set.breather_seconds {
+                return;
+            }
+            let Some(wave) = wave_set.waves.get(state.current) else {
+                state.phase = WavePhase::Finished;
+                return;
+            };
+            info!("wave {}: {}", state.current + 1, wave.name);
+            spawn_mode.0 = wave.spawn;
+            state.pending = wave
+                .groups
+                .iter()
+                .enumerate()
+                .flat_map(|(group, def)| {
+                    (0..def.count).map(move |member| PendingSpawn {
+                        at: def.delay + def.interval * member as f32,
+                        group,
+                        member,
+                    })
+                })
+                .collect();
+            state.anchors = vec![None; wave.groups.len()];
+            state.kills = 0;
+            state.phase_seconds = 0f32;
+            state.phase = WavePhase::Running;
+        }
+        WavePhase::Running => {
+            let wave = &wave_set.waves[state.current];
+            state.kills += kills;
+
+            let players = q_players
+                .iter()
+                .map(|transform| transform.translation.xy())
+                .collect::<Vec<_>>();
+            let player = players.first().copied().unwrap_or(Vec2::ZERO);
+            let rng = &mut run_rng.0;
+            let now = state.phase_seconds;
+            let state = &mut *state;
+            state.pending.retain(|pending| {
+                if pending.at > now {
+                    return true;
+                }
+                let group = &wave.groups[pending.group];
+                let anchor = match (group.formation, state.anchors[pending.group]) {
+                    (Formation::Scattered, _) | (_, None) => {
+                        spawn_def.pick_position(spawn_mode.0, &players, rng)
+                    }
+                    (_, Some(anchor)) => anchor,
+                };
+                state.anchors[pending.group] = Some(anchor);
+                let position = formation_position(
+                    group.formation,
+                    anchor,
+                    player,
+                    pending.member,
+                    group.count,
+                    rng,
+                );
+                spawn_queue.push(
+                    &spawn_def,
+                    QueuedSpawn {
+                        kind: group.kind,
+                        position: Some(position),
+                        affixes: affix_def.roll(run_time.0.elapsed_secs(), rng),
+                        telegraph: true,
+                    },
+                );
+                false
+            });
+
+            let cleared = match wave.clear {
+                ClearCondition::AllDead => {
+                    state.pending.is_empty() && spawn_queue.0.is_empty() && q_alive.is_empty()
+                }
+                ClearCondition::Survive(seconds) => state.phase_seconds >= seconds,
+                ClearCondition::Kills(count) => state.kills >= count,
+            };
+            if cleared {
+                info!("wave {} cleared", state.current + 1);
+                state.pending.clear();
+                spawn_queue.0.clear();
+                state.current += 1;
+                state.phase_seconds = 0f32;
+                state.phase = if state.current < wave_set.waves.len() {
+                    WavePhase::Breather
+                } else {
+                    WavePhase::Finished
+                };
+            }
+        }
+    }
+}
+
+pub fn display_wave_banner(
+    mode: Res<GameMode>,
+    state: Res<WaveState>,
+    wave_assets: Res<WaveAssets>,
+    wave_sets: Res<Assets<WaveSet>>,
+    mut q_banner: Query<(&mut Text, &mut Style), With<WaveBanner>>,
+) {
+    let Ok((mut text, mut style)) = q_banner.get_single_mut() else {
+        return;
+    };
+    let wave_set = wave_sets.get(&wave_assets.waves);
+    let banner = match (state.phase, wave_set) {
+        _ if *mode != GameMode::Waves => None,
+        (WavePhase::Breather, Some(wave_set)) => wave_set.waves.get(state.current).map(|wave| {
+            let remaining = (wave_set.breather_seconds - state.phase_seconds).max(0f32);
+            format!(
+                "Wave {}: {}\n{:.0}",
+                state.current + 1,
+                wave.name,
+                remaining.ceil()
+            )
+        }),
+        (WavePhase::Finished, _) => Some("All waves cleared!".to_string()),
+        _ => None,
+    };
+    match banner {
+        Some(banner) => {
+            text.sections[0].value = banner;
+            style.display = Display::DEFAULT;
+        }
+        None => style.display = Display::None,
+    }
+}